@@ -0,0 +1,237 @@
+use crate::expr::Expr;
+use crate::stmt::{FunctionDecl, Stmt};
+
+/// Prints syntax trees as parenthesized prefix expressions, e.g. `1 + 2 * 3`
+/// as `(+ 1 (* 2 3))`, making precedence and desugaring easy to see.
+pub(crate) struct AstPrinter;
+
+impl AstPrinter {
+    //Statements
+    pub(crate) fn print(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(statements) => self.parenthesize_stmts("block", statements),
+            Stmt::Class { name, superclass, traits, fields, methods, class_methods } => {
+                let mut s = format!("(class {}", name.lexeme);
+                if let Some(superclass) = superclass {
+                    s.push_str(&format!(" < {}", self.print_expr(superclass)));
+                }
+                if !traits.is_empty() {
+                    let traits: Vec<String> = traits.iter().map(|t| self.print_expr(t)).collect();
+                    s.push_str(&format!(" with {}", traits.join(" ")));
+                }
+                for f in fields {
+                    s.push_str(&format!(" (field {})", f.lexeme));
+                }
+                for m in methods {
+                    s.push(' ');
+                    s.push_str(&self.function(if m.is_getter { "getter" } else { "method" }, m));
+                }
+                for m in class_methods {
+                    s.push(' ');
+                    s.push_str(&self.function(if m.is_getter { "class getter" } else { "class" }, m));
+                }
+                s.push(')');
+                s
+            }
+            Stmt::DoWhile { body, condition } => {
+                format!("(do {} {})", self.print(body), self.print_expr(condition))
+            }
+            Stmt::Destructure { names, initializer, .. } => {
+                let names: Vec<&str> = names.iter().map(|n| n.lexeme.as_str()).collect();
+                format!("(var [{}] {})", names.join(" "), self.print_expr(initializer))
+            }
+            Stmt::Error(tokens) => {
+                let lexemes: Vec<&str> = tokens.iter().map(|t| t.lexeme.as_str()).collect();
+                format!("(error {})", lexemes.join(" "))
+            }
+            Stmt::Expression(expr) => self.parenthesize(";", &[expr]),
+            Stmt::Function(decl) => self.function("fun", decl),
+            Stmt::If { condition, then_branch, else_branch } => {
+                let mut s = format!("(if {} {}", self.print_expr(condition), self.print(then_branch));
+                if let Some(else_branch) = else_branch {
+                    s.push_str(&format!(" {}", self.print(else_branch)));
+                }
+                s.push(')');
+                s
+            }
+            Stmt::Print(expr) => self.parenthesize("print", &[expr]),
+            Stmt::Return { value: Some(value), .. } => self.parenthesize("return", &[value]),
+            Stmt::Return { value: None, .. } => "(return)".to_string(),
+            Stmt::Trait { name, methods } => {
+                let mut s = format!("(trait {}", name.lexeme);
+                for m in methods {
+                    s.push(' ');
+                    s.push_str(&self.function(if m.is_getter { "getter" } else { "method" }, m));
+                }
+                s.push(')');
+                s
+            }
+            Stmt::Var { name, initializer: Some(init) } => {
+                format!("(var {} {})", name.lexeme, self.print_expr(init))
+            }
+            Stmt::Var { name, initializer: None } => format!("(var {})", name.lexeme),
+            Stmt::While { condition, body } => {
+                format!("(while {} {})", self.print_expr(condition), self.print(body))
+            }
+        }
+    }
+
+    //Expressions
+    pub(crate) fn print_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign { name, value, .. } => self.parenthesize(&format!("= {}", name.lexeme), &[value]),
+            Expr::Binary { left, operator, right } | Expr::Logical { left, operator, right } => {
+                self.parenthesize(&operator.lexeme, &[left, right])
+            }
+            Expr::Call { callee, arguments, .. } => {
+                let mut parts = vec![callee.as_ref()];
+                parts.extend(arguments);
+                self.parenthesize("call", &parts)
+            }
+            Expr::Compound { target, operator, value } => self.parenthesize(&operator.lexeme, &[target, value]),
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                self.parenthesize("?:", &[condition, then_branch, else_branch])
+            }
+            Expr::Function(decl) => self.function("fun", decl),
+            Expr::Get { object, name } => self.parenthesize(&format!(". {}", name.lexeme), &[object]),
+            Expr::Grouping(expr) => self.parenthesize("group", &[expr]),
+            Expr::Index { object, index, .. } => self.parenthesize("[]", &[object, index]),
+            Expr::Interpolation(parts) => {
+                let parts: Vec<&Expr> = parts.iter().collect();
+                self.parenthesize("interpolate", &parts)
+            }
+            Expr::List(elements) => {
+                let elements: Vec<&Expr> = elements.iter().collect();
+                self.parenthesize("list", &elements)
+            }
+            Expr::Literal(literal) => literal.to_string(),
+            Expr::Map { entries, .. } => {
+                let parts: Vec<&Expr> = entries.iter().flat_map(|(k, v)| [k, v]).collect();
+                self.parenthesize("map", &parts)
+            }
+            Expr::Set { object, name, value } => {
+                self.parenthesize(&format!("=. {}", name.lexeme), &[object, value])
+            }
+            Expr::SetIndex { object, index, value, .. } => self.parenthesize("[]=", &[object, index, value]),
+            Expr::Slice { object, start, end, .. } => {
+                let bound = |b: &Option<Box<Expr>>| b.as_ref().map_or("_".to_string(), |b| self.print_expr(b));
+                format!("([..] {} {} {})", self.print_expr(object), bound(start), bound(end))
+            }
+            Expr::Super { method, .. } => format!("(super {})", method.lexeme),
+            Expr::This { .. } => "this".to_string(),
+            Expr::Unary { operator, right } => self.parenthesize(&operator.lexeme, &[right]),
+            Expr::Update { target, operator, prefix } => {
+                let name = if *prefix { format!("{} _", operator.lexeme) } else { format!("_ {}", operator.lexeme) };
+                self.parenthesize(&name, &[target])
+            }
+            Expr::Variable { name, .. } => name.lexeme.clone(),
+        }
+    }
+
+    //Helpers
+    fn parenthesize(&self, name: &str, exprs: &[&Expr]) -> String {
+        let mut s = format!("({}", name);
+        for e in exprs {
+            s.push(' ');
+            s.push_str(&self.print_expr(e));
+        }
+        s.push(')');
+        s
+    }
+
+    fn parenthesize_stmts(&self, name: &str, statements: &[Stmt]) -> String {
+        let mut s = format!("({}", name);
+        for stmt in statements {
+            s.push(' ');
+            s.push_str(&self.print(stmt));
+        }
+        s.push(')');
+        s
+    }
+
+    fn function(&self, kind: &str, decl: &FunctionDecl) -> String {
+        let params: Vec<&str> = decl.params.iter().map(|p| p.lexeme.as_str()).collect();
+        let name = if decl.is_anonymous() {
+            format!("{} ({})", kind, params.join(" "))
+        } else if decl.is_getter {
+            format!("{} {}", kind, decl.name.lexeme)
+        } else {
+            format!("{} {} ({})", kind, decl.name.lexeme, params.join(" "))
+        };
+        self.parenthesize_stmts(&name, &decl.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn print(input: &str) -> Vec<String> {
+        let mut scanner = Scanner::new(input.to_string());
+        let stmts = Parser::new(scanner.scan_tokens().unwrap()).parse().unwrap();
+        stmts.iter().map(|s| AstPrinter.print(s)).collect()
+    }
+
+    #[test]
+    fn test_expressions() {
+        assert_eq!(print("-123 * (45.67);"), vec!["(; (* (- 123) (group 45.67)))"]);
+        assert_eq!(print("a = b or c and !d;"), vec!["(; (= a (or b (and c (! d)))))"]);
+        assert_eq!(print("f(1, \"two\").x.y = nil;"), vec!["(; (=. y (. x (call f 1 \"two\")) nil))"]);
+        assert_eq!(print("\"a${b}\";"), vec!["(; (interpolate \"a\" b \"\"))"]);
+        assert_eq!(print("a ? b : c ? d : e;"), vec!["(; (?: a b (?: c d e)))"]);
+        assert_eq!(print("xs[0] = [1, ys[1]];"), vec!["(; ([]= xs 0 (list 1 ([] ys 1))))"]);
+        assert_eq!(print("a.b += c; xs[i] -= 1;"), vec!["(; (+= (. b a) c))", "(; (-= ([] xs i) 1))"]);
+        assert_eq!(print("i++; --a.b;"), vec!["(; (_ ++ i))", "(; (-- _ (. b a)))"]);
+        assert_eq!(print("s[1..-1]; s[..2];"), vec!["(; ([..] s 1 (- 1)))", "(; ([..] s _ 2))"]);
+        assert_eq!(print("print {\"a\": 1, b: c};"), vec!["(print (map \"a\" 1 b c))"]);
+    }
+
+    #[test]
+    fn test_statements() {
+        assert_eq!(print("var a; var b = 1; print b;"), vec!["(var a)", "(var b 1)", "(print b)"]);
+        assert_eq!(
+            print("for (var i = 0; i < 3; i = i + 1) print i;"),
+            vec!["(block (var i 0) (while (< i 3) (block (print i) (; (= i (+ i 1))))))"],
+        );
+        assert_eq!(print("if (a) { return; } else b;"), vec!["(if a (block (return)) (; b))"]);
+        assert_eq!(print("var a = 1, [b, c] = d;"), vec!["(var a 1)", "(var [b c] d)"]);
+        assert_eq!(print("do { a; } while (b);"), vec!["(do (block (; a)) b)"]);
+    }
+
+    #[test]
+    fn test_partial_trees() {
+        let mut scanner = Scanner::new("var 1 = 2;\nprint a;\n{ print (; b; }".to_string());
+        let (stmts, errors) = Parser::new(scanner.scan_tokens().unwrap()).parse_partial();
+        let printed: Vec<String> = stmts.iter().map(|s| AstPrinter.print(s)).collect();
+        assert_eq!(printed, vec!["(error var 1 = 2 ;)", "(print a)", "(block (error print ( ;) (; b))"]);
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn test_functions_and_classes() {
+        assert_eq!(print("fun add(a, b) { return a + b; }"), vec!["(fun add (a b) (return (+ a b)))"]);
+        assert_eq!(print("var id = fun (a) { return a; };"), vec!["(var id (fun (a) (return a)))"]);
+        assert_eq!(
+            print("class B < A { init() { this.x = super.init; } }"),
+            vec!["(class B < A (method init () (; (=. x this (super init)))))"],
+        );
+        assert_eq!(
+            print("class Math { class square(n) { return n * n; } }"),
+            vec!["(class Math (class square (n) (return (* n n))))"],
+        );
+        assert_eq!(
+            print("class Point { var x, y; var z; init() {} }"),
+            vec!["(class Point (field x) (field y) (field z) (method init ()))"],
+        );
+        assert_eq!(
+            print("trait T { m() {} } class C < B with T, U {}"),
+            vec!["(trait T (method m ()))", "(class C < B with T U)"],
+        );
+        assert_eq!(
+            print("class Circle { area { return this.r; } class unit { return 1; } }"),
+            vec!["(class Circle (getter area (return (. r this))) (class getter unit (return 1)))"],
+        );
+    }
+}
@@ -0,0 +1,13 @@
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::token::Token;
+use crate::value::Value;
+
+/// Anything that can be invoked with `()` from Lox code. `paren` is the call's
+/// closing parenthesis, for reporting errors raised by the call itself.
+/// Callables are `Send + Sync` so a whole session can move between threads.
+pub trait LoxCallable: Send + Sync {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, RuntimeError>;
+    fn to_string(&self) -> String;
+}
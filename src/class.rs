@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::callable::LoxCallable;
+use crate::error::RuntimeError;
+use crate::function::LoxFunction;
+use crate::interpreter::Interpreter;
+use crate::token::Token;
+use crate::value::Value;
+
+pub struct LoxClass {
+    pub(crate) name: String,
+    superclass: Option<Arc<LoxClass>>,
+    /// Instance fields declared with `var` in the class body.
+    declared_fields: Vec<String>,
+    methods: HashMap<String, Arc<LoxFunction>>,
+    class_methods: HashMap<String, Arc<LoxFunction>>,
+    /// Properties set on the class itself, like `Math.pi = 3.14;`.
+    fields: RwLock<HashMap<String, Value>>,
+}
+
+impl LoxClass {
+    pub(crate) fn new(
+        name: &str,
+        superclass: Option<Arc<LoxClass>>,
+        declared_fields: Vec<String>,
+        methods: HashMap<String, Arc<LoxFunction>>,
+        class_methods: HashMap<String, Arc<LoxFunction>>,
+    ) -> Self {
+        LoxClass { name: name.to_string(), superclass, declared_fields, methods, class_methods, fields: RwLock::default() }
+    }
+
+    /// Whether this class or a superclass declares the field `name`.
+    pub(crate) fn declares_field(&self, name: &str) -> bool {
+        self.declared_fields.iter().any(|f| f == name)
+            || self.superclass.as_ref().is_some_and(|superclass| superclass.declares_field(name))
+    }
+
+    /// Instances of classes that declare fields can only set those fields.
+    pub(crate) fn has_declared_fields(&self) -> bool {
+        !self.declared_fields.is_empty()
+            || self.superclass.as_ref().is_some_and(|superclass| superclass.has_declared_fields())
+    }
+
+    /// Looks the method up on this class, then up the superclass chain.
+    pub(crate) fn find_method(&self, name: &str) -> Option<Arc<LoxFunction>> {
+        if let Some(method) = self.methods.get(name) {
+            return Some(Arc::clone(method));
+        }
+
+        self.superclass.as_ref().and_then(|superclass| superclass.find_method(name))
+    }
+
+    pub(crate) fn find_class_method(&self, name: &str) -> Option<Arc<LoxFunction>> {
+        if let Some(method) = self.class_methods.get(name) {
+            return Some(Arc::clone(method));
+        }
+
+        self.superclass.as_ref().and_then(|superclass| superclass.find_class_method(name))
+    }
+
+    /// Like instances, fields shadow methods. Both are inherited, and class
+    /// methods come back bound to `class`, or run if they're getters.
+    pub(crate) fn get(class: &Arc<LoxClass>, interpreter: &mut Interpreter, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = class.find_field(&name.lexeme) {
+            return Ok(value);
+        }
+
+        match class.find_class_method(&name.lexeme) {
+            Some(method) => method.access(Value::Class(Arc::clone(class)), interpreter, name),
+            None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        }
+    }
+
+    /// Sets the field on this class, shadowing any inherited one.
+    pub(crate) fn set(&self, name: &Token, value: Value) {
+        self.fields.write().unwrap().insert(name.lexeme.clone(), value);
+    }
+
+    fn find_field(&self, name: &str) -> Option<Value> {
+        if let Some(value) = self.fields.read().unwrap().get(name) {
+            return Some(value.clone());
+        }
+
+        self.superclass.as_ref().and_then(|superclass| superclass.find_field(name))
+    }
+
+    /// Calling a class takes the arguments of its `init` method, if it has one.
+    pub(crate) fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    /// Creates a new instance and runs `init` on it.
+    pub(crate) fn instantiate(class: &Arc<LoxClass>, interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let instance = Arc::new(RwLock::new(LoxInstance::new(Arc::clone(class))));
+
+        if let Some(initializer) = class.find_method("init") {
+            initializer.bind(Value::Instance(Arc::clone(&instance))).call(interpreter, paren, arguments)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+/// A named bundle of methods, copied into each class that mixes it in.
+pub struct LoxTrait {
+    pub(crate) name: String,
+    methods: HashMap<String, Arc<LoxFunction>>,
+}
+
+impl LoxTrait {
+    pub(crate) fn new(name: &str, methods: HashMap<String, Arc<LoxFunction>>) -> Self {
+        LoxTrait { name: name.to_string(), methods }
+    }
+
+    /// The methods a class gets from `traits`, given the names of the methods
+    /// it declares itself, which take precedence. A method that two traits
+    /// both provide, and the class doesn't override, is an error. Methods are
+    /// checked in name order, so the same conflict is reported every run.
+    pub(crate) fn mix_in(traits: &[Arc<LoxTrait>], own: &HashMap<String, Arc<LoxFunction>>) -> Result<HashMap<String, Arc<LoxFunction>>, String> {
+        let mut methods = HashMap::new();
+        let mut providers: HashMap<&str, &str> = HashMap::new();
+
+        for t in traits {
+            let mut inherited: Vec<_> = t.methods.iter().filter(|(name, _)| !own.contains_key(*name)).collect();
+            inherited.sort_by_key(|(name, _)| *name);
+            for (name, method) in inherited {
+                if let Some(other) = providers.insert(name, &t.name) {
+                    return Err(conflict(name, other, &t.name));
+                }
+                methods.insert(name.clone(), Arc::clone(method));
+            }
+        }
+        Ok(methods)
+    }
+}
+
+/// The error for `method` coming from two traits mixed into one class.
+pub(crate) fn conflict(method: &str, first: &str, second: &str) -> String {
+    format!("Method '{}' is provided by both '{}' and '{}'.", method, first, second)
+}
+
+pub struct LoxInstance {
+    pub(crate) class: Arc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxInstance {
+    pub(crate) fn new(class: Arc<LoxClass>) -> Self {
+        LoxInstance { class, fields: HashMap::new() }
+    }
+
+    /// Fields shadow methods; methods come back bound to this instance, and
+    /// getters are run. Reading a declared field before it's set is an error.
+    pub(crate) fn get(instance: &Arc<RwLock<LoxInstance>>, interpreter: &mut Interpreter, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = instance.read().unwrap().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.read().unwrap().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => method.access(Value::Instance(Arc::clone(instance)), interpreter, name),
+            None if instance.read().unwrap().class.declares_field(&name.lexeme) => {
+                Err(RuntimeError::new(name, &format!("Field '{}' was read before it was set.", name.lexeme)))
+            }
+            None => Err(RuntimeError::new(name, &format!("Undefined property '{}'.", name.lexeme))),
+        }
+    }
+
+    /// Catches typos in field names for classes that declare their fields.
+    pub(crate) fn set(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if self.class.has_declared_fields() && !self.class.declares_field(&name.lexeme) {
+            let message = format!("Undeclared field '{}' on '{}'.", name.lexeme, self.class.name);
+            return Err(RuntimeError::new(name, &message));
+        }
+
+        self.fields.insert(name.lexeme.clone(), value);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use crate::error::{ParseError, ResolveError, RuntimeError, ScanError};
+use crate::token::{Span, Token};
+
+/// Static errors stop a program before it runs; a runtime error stops it partway.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    RuntimeError,
+}
+
+/// An error from any phase, as reported to the user.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Diagnostic {
+    Scan(ScanError),
+    Parse(ParseError),
+    Resolve(ResolveError),
+    Runtime(RuntimeError),
+}
+
+impl Diagnostic {
+    pub fn severity(&self) -> Severity {
+        match self {
+            Diagnostic::Runtime(_) => Severity::RuntimeError,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The token the error points at. Scan errors happen before there is one.
+    pub fn token(&self) -> Option<&Token> {
+        match self {
+            Diagnostic::Scan(_) => None,
+            Diagnostic::Parse(e) => Some(&e.token),
+            Diagnostic::Resolve(e) => Some(&e.token),
+            Diagnostic::Runtime(e) => Some(&e.token),
+        }
+    }
+
+    /// The bare message, without the location `Display` adds.
+    pub fn message(&self) -> &str {
+        match self {
+            Diagnostic::Scan(e) => &e.message,
+            Diagnostic::Parse(e) => &e.message,
+            Diagnostic::Resolve(e) => &e.message,
+            Diagnostic::Runtime(e) => &e.message,
+        }
+    }
+
+    pub fn line(&self) -> u32 {
+        match self {
+            Diagnostic::Scan(e) => e.line,
+            d => d.token().unwrap().line,
+        }
+    }
+
+    /// The 1-based column, in chars, where the error starts.
+    pub fn column(&self) -> u32 {
+        match self {
+            Diagnostic::Scan(e) => e.column,
+            d => d.token().unwrap().column,
+        }
+    }
+
+    /// Byte offsets in the source of the text the error is about.
+    pub fn span(&self) -> Span {
+        match self {
+            Diagnostic::Scan(e) => e.span,
+            d => d.token().unwrap().span,
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Diagnostic::Scan(e) => e.fmt(f),
+            Diagnostic::Parse(e) => e.fmt(f),
+            Diagnostic::Resolve(e) => e.fmt(f),
+            Diagnostic::Runtime(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<ScanError> for Diagnostic {
+    fn from(e: ScanError) -> Self {
+        Diagnostic::Scan(e)
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(e: ParseError) -> Self {
+        Diagnostic::Parse(e)
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(e: ResolveError) -> Self {
+        Diagnostic::Resolve(e)
+    }
+}
+
+impl From<RuntimeError> for Diagnostic {
+    fn from(e: RuntimeError) -> Self {
+        Diagnostic::Runtime(e)
+    }
+}
+
+/// Every diagnostic reported during a session, in the order they were reported.
+#[derive(Default)]
+pub(crate) struct Diagnostics {
+    reported: Vec<Diagnostic>,
+}
+
+impl Diagnostics {
+    pub(crate) fn new() -> Self {
+        Diagnostics::default()
+    }
+
+    pub(crate) fn report(&mut self, diagnostic: impl Into<Diagnostic>) -> &Diagnostic {
+        self.reported.push(diagnostic.into());
+        self.reported.last().unwrap()
+    }
+
+    pub(crate) fn had_error(&self) -> bool {
+        self.reported.iter().any(|d| d.severity() == Severity::Error)
+    }
+
+    pub(crate) fn had_runtime_error(&self) -> bool {
+        self.reported.iter().any(|d| d.severity() == Severity::RuntimeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::TokenType::PLUS;
+
+    #[test]
+    fn test_severity_flags() {
+        let mut diagnostics = Diagnostics::new();
+        assert!(!diagnostics.had_error());
+        assert!(!diagnostics.had_runtime_error());
+
+        let plus = Token::new(PLUS, "+", 2);
+        diagnostics.report(RuntimeError::new(&plus, "Operands must be numbers."));
+        assert!(!diagnostics.had_error());
+        assert!(diagnostics.had_runtime_error());
+
+        diagnostics.report(ScanError::new(1, "Unexpected character."));
+        assert!(diagnostics.had_error());
+    }
+
+    #[test]
+    fn test_diagnostics_display_like_their_errors() {
+        let plus = Token::new(PLUS, "+", 2);
+        let parse = Diagnostic::from(ParseError::new(&plus, "Expect expression."));
+        let scan = Diagnostic::from(ScanError::new(1, "Unexpected character."));
+
+        assert_eq!(parse.to_string(), "[line 2] Error at '+': Expect expression.");
+        assert_eq!(parse.token(), Some(&plus));
+        assert_eq!(scan.to_string(), "[line 1] Error: Unexpected character.");
+        assert_eq!(scan.token(), None);
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::error::RuntimeError;
+use crate::token::Token;
+use crate::value::Value;
+
+/// A single scope of variable bindings, chained to the scope that encloses it.
+/// Scopes are shared, since a closure keeps the scope it was declared in alive
+/// after that scope's block has finished.
+#[derive(Default, Clone)]
+pub(crate) struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Arc<RwLock<Environment>>>,
+}
+
+impl Environment {
+    pub(crate) fn new() -> Self {
+        Environment::default()
+    }
+
+    pub(crate) fn new_enclosing(enclosing: Arc<RwLock<Environment>>) -> Self {
+        Environment { values: HashMap::new(), enclosing: Some(enclosing) }
+    }
+
+    /// The bindings in this scope alone, in no particular order.
+    pub(crate) fn bindings(&self) -> impl Iterator<Item = (&String, &Value)> {
+        self.values.iter()
+    }
+
+    pub(crate) fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_string(), value);
+    }
+
+    pub(crate) fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.read().unwrap().get(name),
+            None => Err(RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))),
+        }
+    }
+
+    /// Reads `name` from the scope exactly `distance` hops out from `env`,
+    /// as computed by the resolver.
+    pub(crate) fn get_at(env: &Arc<RwLock<Environment>>, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        Self::ancestor(env, distance).read().unwrap().get(name)
+    }
+
+    pub(crate) fn assign_at(env: &Arc<RwLock<Environment>>, distance: usize, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        Self::ancestor(env, distance).write().unwrap().assign(name, value)
+    }
+
+    fn ancestor(env: &Arc<RwLock<Environment>>, distance: usize) -> Arc<RwLock<Environment>> {
+        let mut env = Arc::clone(env);
+        for _ in 0..distance {
+            let enclosing = env.read().unwrap().enclosing.clone().expect("resolved scope exists");
+            env = enclosing;
+        }
+        env
+    }
+
+    pub(crate) fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(v) = self.values.get_mut(&name.lexeme) {
+            *v = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.write().unwrap().assign(name, value),
+            None => Err(RuntimeError::new(name, &format!("Undefined variable '{}'.", name.lexeme))),
+        }
+    }
+}
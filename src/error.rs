@@ -0,0 +1,183 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::diagnostics::Diagnostic;
+use crate::token::{Span, Token};
+use crate::token::TokenType::EOF;
+
+/// A lexical error, such as an unexpected character or an unterminated string.
+/// `span` covers the offending text; for input that ends too soon it is the
+/// empty span at the end.
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub line: u32,
+    pub column: u32,
+    pub span: Span,
+    pub message: String,
+}
+
+/// Like tokens, scan errors compare by line and message only.
+impl PartialEq for ScanError {
+    fn eq(&self, other: &Self) -> bool {
+        self.line == other.line && self.message == other.message
+    }
+}
+
+impl ScanError {
+    pub(crate) fn new(line: u32, message: &str) -> Self {
+        ScanError { line, column: 0, span: Span::default(), message: message.to_string() }
+    }
+
+    /// Whether the input ended partway through a string or comment, which more
+    /// input could fix.
+    pub(crate) fn is_unterminated(&self) -> bool {
+        self.message.starts_with("Unterminated")
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.message)
+    }
+}
+
+impl Error for ScanError {}
+
+/// A syntax error, reported at the token where parsing went wrong.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(token: &Token, message: &str) -> Self {
+        ParseError { token: token.clone(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.token.token_type == EOF {
+            write!(f, "[line {}] Error at end: {}", self.token.line, self.message)
+        } else {
+            write!(f, "[line {}] Error at '{}': {}", self.token.line, self.token.lexeme, self.message)
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// A call to a Lox function that a runtime error unwound through: the
+/// function's name and the line it was called from.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    pub function: String,
+    pub line: u32,
+}
+
+/// An error raised while evaluating, reported at the operator or name that
+/// failed. `trace` lists the function calls it unwound through, innermost
+/// first, and is empty for an error in top-level code.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuntimeError {
+    pub token: Token,
+    pub message: String,
+    pub trace: Vec<Frame>,
+}
+
+impl RuntimeError {
+    pub(crate) fn new(token: &Token, message: &str) -> Self {
+        RuntimeError { token: token.clone(), message: message.to_string(), trace: vec![] }
+    }
+}
+
+/// Prints like jlox, `message` then `[line N]`, with a line per frame when the
+/// error happened inside a function:
+///
+/// ```text
+/// Operand must be a number.
+/// [line 2] in b()
+/// [line 1] in a()
+/// [line 4] in script
+/// ```
+///
+/// A line repeated by runaway recursion is printed once, with a count.
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Each frame's function goes with the line reached inside it.
+        let mut lines = vec![];
+        let mut line = self.token.line;
+        for frame in &self.trace {
+            lines.push(format!("[line {}] in {}()", line, frame.function));
+            line = frame.line;
+        }
+        lines.push(if self.trace.is_empty() { format!("[line {}]", line) } else { format!("[line {}] in script", line) });
+
+        write!(f, "{}", self.message)?;
+        let mut i = 0;
+        while i < lines.len() {
+            let repeats = lines[i..].iter().take_while(|l| **l == lines[i]).count();
+            write!(f, "\n{}", lines[i])?;
+            if repeats > 1 {
+                write!(f, "\n[previous line repeated {} more times]", repeats - 1)?;
+            }
+            i += repeats;
+        }
+        Ok(())
+    }
+}
+
+impl Error for RuntimeError {}
+
+/// A static error found by the resolver, such as returning from top-level code.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResolveError {
+    pub token: Token,
+    pub message: String,
+}
+
+impl ResolveError {
+    pub(crate) fn new(token: &Token, message: &str) -> Self {
+        ResolveError { token: token.clone(), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error at '{}': {}", self.token.line, self.token.lexeme, self.message)
+    }
+}
+
+impl Error for ResolveError {}
+
+/// Why running a program failed: the static errors that kept it from running
+/// at all, or the runtime error that stopped it partway.
+#[derive(Debug, PartialEq, Clone)]
+pub enum LoxError {
+    Compile(Vec<Diagnostic>),
+    Runtime(RuntimeError),
+}
+
+impl LoxError {
+    pub fn into_diagnostics(self) -> Vec<Diagnostic> {
+        match self {
+            LoxError::Compile(diagnostics) => diagnostics,
+            LoxError::Runtime(e) => vec![e.into()],
+        }
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoxError::Compile(diagnostics) => {
+                let lines: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+                write!(f, "{}", lines.join("\n"))
+            }
+            LoxError::Runtime(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for LoxError {}
@@ -1 +1,57 @@
-use crate::token::Token; use std::any::Any;pub(crate) trait Expr {} pub(crate) struct Binary { pub(crate) left: Box<dyn Expr>, pub(crate) operator: Token, pub(crate) right: Box<dyn Expr> } impl Expr for Binary { } pub(crate) struct Grouping { pub(crate) expr: Box<dyn Expr> } impl Expr for Grouping { } pub(crate) struct Literal { pub(crate) value: Box<dyn Any> } impl Expr for Literal { } pub(crate) struct Unary { pub(crate) operator: Token, pub(crate) right: Box<dyn Expr> } impl Expr for Unary { } 
\ No newline at end of file
+use std::sync::{Arc, OnceLock};
+
+use crate::stmt::FunctionDecl;
+use crate::token::{Literal, Token};
+
+/// `depth` on variable accesses is filled in by the resolver: the number of
+/// scopes between the access and the declaration, left unset for a global.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Expr {
+    Assign { name: Token, value: Box<Expr>, depth: OnceLock<usize> },
+    Binary { left: Box<Expr>, operator: Token, right: Box<Expr> },
+    Call { callee: Box<Expr>, paren: Token, arguments: Vec<Expr> },
+    /// `target += value` and the like. `operator` is the binary operator, with
+    /// the compound lexeme for errors. Like `Update`, `target` is evaluated
+    /// only once.
+    Compound { target: Box<Expr>, operator: Token, value: Box<Expr> },
+    /// `condition ? then_branch : else_branch`; only the chosen branch is evaluated.
+    Conditional { condition: Box<Expr>, then_branch: Box<Expr>, else_branch: Box<Expr> },
+    /// An anonymous function, `fun (params) { body }`.
+    Function(Arc<FunctionDecl>),
+    Get { object: Box<Expr>, name: Token },
+    Grouping(Box<Expr>),
+    /// `object[index]`. `bracket` is the closing bracket, for errors.
+    Index { object: Box<Expr>, bracket: Token, index: Box<Expr> },
+    /// An interpolated string: literal pieces and embedded expressions, in
+    /// source order, concatenated as strings.
+    Interpolation(Vec<Expr>),
+    /// A list literal, `[a, b, c]`.
+    List(Vec<Expr>),
+    Literal(Literal),
+    Logical { left: Box<Expr>, operator: Token, right: Box<Expr> },
+    /// A map literal, `{key: value, ...}`. `brace` is the closing brace, for
+    /// errors about keys.
+    Map { brace: Token, entries: Vec<(Expr, Expr)> },
+    Set { object: Box<Expr>, name: Token, value: Box<Expr> },
+    SetIndex { object: Box<Expr>, bracket: Token, index: Box<Expr>, value: Box<Expr> },
+    /// `object[start..end]`; a missing bound means the start or end of the
+    /// string or list.
+    Slice { object: Box<Expr>, bracket: Token, start: Option<Box<Expr>>, end: Option<Box<Expr>> },
+    Super { keyword: Token, method: Token, depth: OnceLock<usize> },
+    This { keyword: Token, depth: OnceLock<usize> },
+    Unary { operator: Token, right: Box<Expr> },
+    /// `++target`, `target++`, `--target` or `target--`. `target` is a
+    /// variable, property or index expression, evaluated only once.
+    Update { target: Box<Expr>, operator: Token, prefix: bool },
+    Variable { name: Token, depth: OnceLock<usize> },
+}
+
+impl Expr {
+    pub(crate) fn variable(name: Token) -> Self {
+        Expr::Variable { name, depth: OnceLock::new() }
+    }
+
+    pub(crate) fn assign(name: Token, value: Expr) -> Self {
+        Expr::Assign { name, value: Box::new(value), depth: OnceLock::new() }
+    }
+}
@@ -0,0 +1,87 @@
+use std::sync::{Arc, RwLock};
+
+use crate::callable::LoxCallable;
+use crate::environment::Environment;
+use crate::error::{Frame, RuntimeError};
+use crate::interpreter::{Interpreter, Unwind, MAX_CALL_DEPTH};
+use crate::stmt::FunctionDecl;
+use crate::token::Token;
+use crate::token::TokenType::THIS;
+use crate::value::Value;
+
+/// A function declared in Lox code, along with the scope it was declared in.
+pub(crate) struct LoxFunction {
+    declaration: Arc<FunctionDecl>,
+    closure: Arc<RwLock<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub(crate) fn new(declaration: Arc<FunctionDecl>, closure: Arc<RwLock<Environment>>, is_initializer: bool) -> Self {
+        LoxFunction { declaration, closure, is_initializer }
+    }
+
+    /// Returns a copy of this method whose body sees `this` as `this`: an
+    /// instance, or the class itself for a class method.
+    pub(crate) fn bind(&self, this: Value) -> LoxFunction {
+        let mut environment = Environment::new_enclosing(Arc::clone(&self.closure));
+        environment.define("this", this);
+        LoxFunction::new(Arc::clone(&self.declaration), Arc::new(RwLock::new(environment)), self.is_initializer)
+    }
+
+    pub(crate) fn name(&self) -> &Token {
+        &self.declaration.name
+    }
+
+    /// The value of this method as a property of `this`: a getter runs and
+    /// gives its result, any other method comes back bound to `this`.
+    pub(crate) fn access(&self, this: Value, interpreter: &mut Interpreter, name: &Token) -> Result<Value, RuntimeError> {
+        let method = self.bind(this);
+        if self.declaration.is_getter {
+            return method.call(interpreter, name, vec![]);
+        }
+        Ok(Value::Callable(Arc::new(method)))
+    }
+
+    fn this(&self) -> Result<Value, RuntimeError> {
+        self.closure.read().unwrap().get(&Token::new(THIS, "this", self.declaration.name.line))
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        let mut environment = Environment::new_enclosing(Arc::clone(&self.closure));
+        for (param, arg) in self.declaration.params.iter().zip(arguments) {
+            environment.define(&param.lexeme, arg);
+        }
+
+        if interpreter.call_depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError::new(paren, "Stack overflow."));
+        }
+        interpreter.call_depth += 1;
+        let result = interpreter.execute_block(&self.declaration.body, Arc::new(RwLock::new(environment)));
+        interpreter.call_depth -= 1;
+
+        // Initializers always hand back the instance, even on an early `return;`.
+        match result {
+            Ok(()) | Err(Unwind::Return(_)) if self.is_initializer => self.this(),
+            Ok(()) => Ok(Value::Nil),
+            Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(mut e)) => {
+                e.trace.push(Frame { function: self.declaration.name.lexeme.clone(), line: paren.line });
+                Err(e)
+            }
+        }
+    }
+
+    fn to_string(&self) -> String {
+        if self.declaration.is_anonymous() {
+            return "<fn>".to_string();
+        }
+        format!("<fn {}>", self.declaration.name.lexeme)
+    }
+}
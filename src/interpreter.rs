@@ -0,0 +1,1761 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance, LoxTrait};
+use crate::environment::Environment;
+use crate::error::RuntimeError;
+use crate::expr::Expr;
+use crate::function::LoxFunction;
+use crate::map::{LoxMap, MapKey};
+use crate::native::{self, NativeFn, NativeFunction};
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::{Token, TokenType};
+use crate::token::TokenType::*;
+use crate::value::Value;
+
+/// Why execution of a statement stopped early: either a runtime error, or a
+/// `return` unwinding to the function call that is executing it.
+pub(crate) enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(e: RuntimeError) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+/// The seed `random` starts from in deterministic mode.
+const DETERMINISTIC_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+/// The most Lox function calls that can be in progress at once. Deeper
+/// recursion is a "Stack overflow." runtime error.
+pub(crate) const MAX_CALL_DEPTH: usize = 1000;
+
+pub struct Interpreter {
+    pub(crate) globals: Arc<RwLock<Environment>>,
+    environment: Arc<RwLock<Environment>>,
+    // Division by zero gives IEEE infinity or NaN, and modulo by zero NaN,
+    // instead of an error.
+    pub(crate) ieee_math: bool,
+    /// How many Lox function calls are in progress, to stop runaway recursion
+    /// before it overflows the Rust stack.
+    pub(crate) call_depth: usize,
+}
+
+impl Interpreter {
+    pub(crate) fn new() -> Self {
+        let globals = Arc::new(RwLock::new(Environment::new()));
+        let mut interpreter = Interpreter { environment: Arc::clone(&globals), globals, ieee_math: false, call_depth: 0 };
+
+        interpreter.define_native("len", 1, Box::new(native::len));
+        interpreter.define_native("substr", 3, Box::new(native::substr));
+        interpreter.define_native("upper", 1, Box::new(native::upper));
+        interpreter.define_native("lower", 1, Box::new(native::lower));
+        interpreter.define_native("contains", 2, Box::new(native::contains));
+        interpreter.define_native("split", 2, Box::new(native::split));
+        interpreter.define_native("push", 2, Box::new(native::push));
+        interpreter.define_native("pop", 1, Box::new(native::pop));
+        interpreter.define_native("keys", 1, Box::new(native::keys));
+        interpreter.define_native("values", 1, Box::new(native::values));
+        interpreter.define_native("remove", 2, Box::new(native::remove));
+        interpreter.define_native("floor", 1, Box::new(native::floor));
+        interpreter.define_native("ceil", 1, Box::new(native::ceil));
+        interpreter.define_native("abs", 1, Box::new(native::abs));
+        interpreter.define_native("sqrt", 1, Box::new(native::sqrt));
+        interpreter.define_native("pow", 2, Box::new(native::pow));
+        interpreter.define_native("min", 2, Box::new(native::min));
+        interpreter.define_native("max", 2, Box::new(native::max));
+        interpreter.define_time_natives(false);
+
+        interpreter
+    }
+
+    /// Defines `random`, `clock` and `sleep`. When `deterministic`, `random`
+    /// always starts from the same seed and time comes from a virtual clock,
+    /// so every run of a script behaves the same.
+    pub(crate) fn define_time_natives(&mut self, deterministic: bool) {
+        if deterministic {
+            let (clock, sleep) = native::new_virtual_clock();
+            self.define_native("random", 0, native::new_random(DETERMINISTIC_SEED));
+            self.define_native("clock", 0, clock);
+            self.define_native("sleep", 1, sleep);
+        } else {
+            self.define_native("random", 0, native::new_random(native::time_seed()));
+            self.define_native("clock", 0, Box::new(native::clock));
+            self.define_native("sleep", 1, Box::new(native::sleep));
+        }
+    }
+
+    pub(crate) fn define_native(&mut self, name: &str, arity: usize, function: Box<NativeFn>) {
+        let native = NativeFunction::new(name, arity, function);
+        self.globals.write().unwrap().define(name, Value::Callable(Arc::new(native)));
+    }
+
+    /// Runs `statements`, returning the value of the last one if it's an
+    /// expression statement, or `nil` otherwise.
+    pub(crate) fn interpret(&mut self, statements: &[Stmt]) -> Result<Value, RuntimeError> {
+        let mut value = Value::Nil;
+        for s in statements {
+            value = match s {
+                Stmt::Expression(expr) => self.evaluate(expr)?,
+                _ => match self.execute(s) {
+                    Ok(()) => Value::Nil,
+                    Err(Unwind::Error(e)) => return Err(e),
+                    Err(Unwind::Return(_)) => return Ok(Value::Nil),
+                },
+            };
+        }
+
+        Ok(value)
+    }
+
+    //Statements
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Unwind> {
+        match stmt {
+            Stmt::Block(statements) => {
+                let environment = Environment::new_enclosing(Arc::clone(&self.environment));
+                self.execute_block(statements, Arc::new(RwLock::new(environment)))?;
+            }
+            Stmt::Class { name, superclass, traits, fields, methods, class_methods } => {
+                let superclass = match superclass {
+                    Some(expr) => match self.evaluate(expr)? {
+                        Value::Class(class) => Some(class),
+                        _ => {
+                            let Expr::Variable { name, .. } = expr else { unreachable!() };
+                            return Err(RuntimeError::new(name, "Superclass must be a class.").into());
+                        }
+                    },
+                    None => None,
+                };
+
+                let mut mixins = vec![];
+                for expr in traits {
+                    match self.evaluate(expr)? {
+                        Value::Trait(t) => mixins.push(t),
+                        _ => {
+                            let Expr::Variable { name, .. } = expr else { unreachable!() };
+                            return Err(RuntimeError::new(name, "Only traits can be mixed in.").into());
+                        }
+                    }
+                }
+
+                // Methods of a subclass close over an extra scope binding `super`.
+                let mut method_closure = Arc::clone(&self.environment);
+                if let Some(superclass) = &superclass {
+                    let mut environment = Environment::new_enclosing(method_closure);
+                    environment.define("super", Value::Class(Arc::clone(superclass)));
+                    method_closure = Arc::new(RwLock::new(environment));
+                }
+
+                let functions = |methods: &[Arc<FunctionDecl>], initializers: bool| -> HashMap<_, _> {
+                    methods.iter().map(|method| {
+                        let is_initializer = initializers && method.name.lexeme == "init";
+                        let function = LoxFunction::new(Arc::clone(method), Arc::clone(&method_closure), is_initializer);
+                        (method.name.lexeme.clone(), Arc::new(function))
+                    }).collect()
+                };
+
+                let mut instance_methods = functions(methods, true);
+                let mixed_in = LoxTrait::mix_in(&mixins, &instance_methods).map_err(|m| RuntimeError::new(name, &m))?;
+                instance_methods.extend(mixed_in);
+
+                let fields = fields.iter().map(|f| f.lexeme.clone()).collect();
+                let class = LoxClass::new(&name.lexeme, superclass, fields, instance_methods, functions(class_methods, false));
+                self.environment.write().unwrap().define(&name.lexeme, Value::Class(Arc::new(class)));
+            }
+            Stmt::Trait { name, methods } => {
+                let methods = methods.iter().map(|method| {
+                    let is_initializer = method.name.lexeme == "init";
+                    let function = LoxFunction::new(Arc::clone(method), Arc::clone(&self.environment), is_initializer);
+                    (method.name.lexeme.clone(), Arc::new(function))
+                }).collect();
+
+                let t = LoxTrait::new(&name.lexeme, methods);
+                self.environment.write().unwrap().define(&name.lexeme, Value::Trait(Arc::new(t)));
+            }
+            Stmt::DoWhile { body, condition } => {
+                loop {
+                    self.execute(body)?;
+                    if !self.evaluate(condition)?.is_truthy() { break; }
+                }
+            }
+            Stmt::Destructure { names, bracket, initializer } => {
+                let Value::List(list) = self.evaluate(initializer)? else {
+                    return Err(RuntimeError::new(bracket, "Only lists can be destructured.").into());
+                };
+                let list = list.read().unwrap();
+                if list.len() != names.len() {
+                    return Err(RuntimeError::new(bracket, &format!(
+                        "Expected a list of length {} but got one of length {}.", names.len(), list.len(),
+                    )).into());
+                }
+
+                let mut environment = self.environment.write().unwrap();
+                for (name, value) in names.iter().zip(list.iter()) {
+                    environment.define(&name.lexeme, value.clone());
+                }
+            }
+            Stmt::Error(_) => unreachable!("trees with errors are never run"),
+            Stmt::Expression(expr) => {
+                self.evaluate(expr)?;
+            }
+            Stmt::Function(declaration) => {
+                let function = LoxFunction::new(Arc::clone(declaration), Arc::clone(&self.environment), false);
+                self.environment.write().unwrap().define(&declaration.name.lexeme, Value::Callable(Arc::new(function)));
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.execute(then_branch)?;
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)?;
+                }
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                println!("{}", self.stringify(&value)?);
+            }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                return Err(Unwind::Return(value));
+            }
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                self.environment.write().unwrap().define(&name.lexeme, value);
+            }
+            Stmt::While { condition, body } => {
+                while self.evaluate(condition)?.is_truthy() {
+                    self.execute(body)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Runs `statements` in `environment`, restoring the current scope
+    /// afterwards even if a statement errors or returns.
+    pub(crate) fn execute_block(&mut self, statements: &[Stmt], environment: Arc<RwLock<Environment>>) -> Result<(), Unwind> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = statements.iter().try_for_each(|s| self.execute(s));
+
+        self.environment = previous;
+        result
+    }
+
+    //Expressions
+    pub(crate) fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Literal(literal) => Ok(Value::from(literal)),
+            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Interpolation(parts) => {
+                let mut s = String::new();
+                for p in parts {
+                    let value = self.evaluate(p)?;
+                    s.push_str(&self.stringify(&value)?);
+                }
+                Ok(Value::Str(s.into()))
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(then_branch)
+                } else {
+                    self.evaluate(else_branch)
+                }
+            }
+            Expr::Function(declaration) => {
+                let function = LoxFunction::new(Arc::clone(declaration), Arc::clone(&self.environment), false);
+                Ok(Value::Callable(Arc::new(function)))
+            }
+            Expr::Logical { left, operator, right } => {
+                let left = self.evaluate(left)?;
+
+                if operator.token_type == OR {
+                    if left.is_truthy() { return Ok(left); }
+                } else if !left.is_truthy() {
+                    return Ok(left);
+                }
+
+                self.evaluate(right)
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.evaluate(right)?;
+
+                match operator.token_type {
+                    BANG => Ok(Value::Bool(!right.is_truthy())),
+                    TILDE => {
+                        let n = Self::number_operand(operator, &right)?;
+                        Ok(Value::Number(!Self::to_bits(n) as f64))
+                    }
+                    _ => {
+                        let n = Self::number_operand(operator, &right)?;
+                        Ok(Value::Number(-n))
+                    }
+                }
+            }
+            Expr::Binary { left, operator, right } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                self.binary(operator, left, right)
+            }
+            Expr::Call { callee, paren, arguments } => {
+                let callee = self.evaluate(callee)?;
+
+                let mut args = vec![];
+                for a in arguments {
+                    args.push(self.evaluate(a)?);
+                }
+
+                match callee {
+                    Value::Callable(function) => {
+                        Self::check_arity(paren, function.arity(), args.len())?;
+                        function.call(self, paren, args)
+                    }
+                    Value::Class(class) => {
+                        Self::check_arity(paren, class.arity(), args.len())?;
+                        LoxClass::instantiate(&class, self, paren, args)
+                    }
+                    _ => Err(RuntimeError::new(paren, "Can only call functions and classes.")),
+                }
+            }
+            Expr::Get { object, name } => {
+                let object = self.evaluate(object)?;
+                self.get_property(&object, name)
+            }
+            Expr::Index { object, bracket, index } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+
+                Self::get_index(bracket, &object, &index)
+            }
+            Expr::List(elements) => {
+                let mut values = vec![];
+                for e in elements {
+                    values.push(self.evaluate(e)?);
+                }
+                Ok(Value::List(Arc::new(RwLock::new(values))))
+            }
+            Expr::Map { brace, entries } => {
+                let mut map = LoxMap::new();
+                for (key, value) in entries {
+                    let key = self.evaluate(key)?;
+                    let key = Self::map_key(brace, &key)?;
+                    map.insert(key, self.evaluate(value)?);
+                }
+                Ok(Value::Map(Arc::new(RwLock::new(map))))
+            }
+            Expr::SetIndex { object, bracket, index, value } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+
+                Self::set_index(bracket, &object, &index, value.clone())?;
+                Ok(value)
+            }
+            Expr::Set { object, name, value } => {
+                let object = self.evaluate(object)?;
+                if !matches!(object, Value::Instance(_) | Value::Class(_)) {
+                    return Err(RuntimeError::new(name, "Only instances and classes have fields."));
+                }
+
+                let value = self.evaluate(value)?;
+                Self::set_property(&object, name, value.clone())?;
+                Ok(value)
+            }
+            Expr::Slice { object, bracket, start, end } => {
+                let object = self.evaluate(object)?;
+                let start = start.as_ref().map(|e| self.evaluate(e)).transpose()?;
+                let end = end.as_ref().map(|e| self.evaluate(e)).transpose()?;
+
+                match object {
+                    Value::Str(s) => {
+                        let (start, end) = Self::slice_bounds(bracket, start, end, s.chars().count(), "string")?;
+                        Ok(Value::Str(s.chars().skip(start).take(end - start).collect::<String>().into()))
+                    }
+                    Value::List(list) => {
+                        let list = list.read().unwrap();
+                        let (start, end) = Self::slice_bounds(bracket, start, end, list.len(), "list")?;
+                        Ok(Value::List(Arc::new(RwLock::new(list[start..end].to_vec()))))
+                    }
+                    _ => Err(RuntimeError::new(bracket, "Only strings and lists can be sliced.")),
+                }
+            }
+            Expr::Super { keyword, method, depth } => {
+                let distance = depth.get().copied().expect("super is always resolved to a local scope");
+                let Value::Class(superclass) = Environment::get_at(&self.environment, distance, keyword)? else {
+                    unreachable!("super is always bound to a class");
+                };
+                // `this` is bound in the scope just inside the one binding `super`.
+                let this = Token::new(THIS, "this", keyword.line);
+                let this = Environment::get_at(&self.environment, distance - 1, &this)?;
+
+                let found = match this {
+                    Value::Class(_) => superclass.find_class_method(&method.lexeme),
+                    _ => superclass.find_method(&method.lexeme),
+                };
+                match found {
+                    Some(m) => m.access(this, self, method),
+                    None => Err(RuntimeError::new(method, &format!("Undefined property '{}'.", method.lexeme))),
+                }
+            }
+            Expr::This { keyword, depth } => self.look_up_variable(keyword, depth.get().copied()),
+            Expr::Variable { name, depth } => self.look_up_variable(name, depth.get().copied()),
+            Expr::Assign { name, value, depth } => {
+                let value = self.evaluate(value)?;
+                self.assign_variable(name, depth.get().copied(), value.clone())?;
+                Ok(value)
+            }
+            Expr::Update { target, operator, prefix } => {
+                let step = if operator.token_type == PLUSPLUS { 1.0 } else { -1.0 };
+                let (old, new) = self.update_target(target, |_, old| {
+                    Ok(Value::Number(Self::number_operand(operator, &old)? + step))
+                })?;
+                Ok(if *prefix { new } else { old })
+            }
+            Expr::Compound { target, operator, value } => {
+                let (_, new) = self.update_target(target, |interpreter, old| {
+                    let right = interpreter.evaluate(value)?;
+                    interpreter.binary(operator, old, right)
+                })?;
+                Ok(new)
+            }
+        }
+    }
+
+    /// How `print` and string interpolation show a value. An instance whose
+    /// class defines `str()` shows as the string that returns, including
+    /// inside lists and maps.
+    pub(crate) fn stringify(&mut self, value: &Value) -> Result<String, RuntimeError> {
+        value.format_with(&mut |instance| self.instance_string(instance))
+    }
+
+    fn instance_string(&mut self, value: &Value) -> Result<String, RuntimeError> {
+        let Value::Instance(instance) = value else { return Ok(value.to_string()) };
+        let Some(method) = instance.read().unwrap().class.find_method("str") else { return Ok(value.to_string()) };
+
+        match self.call_method(instance, &method, method.name(), vec![])? {
+            Value::Str(s) => Ok(s.to_string()),
+            _ => Err(RuntimeError::new(method.name(), "Method 'str' must return a string.")),
+        }
+    }
+
+    /// The method a class can define to overload a binary operator for its
+    /// instances, when they're the left operand. `!=` is the negation of `eq`.
+    fn operator_method(operator: TokenType) -> Option<&'static str> {
+        Some(match operator {
+            PLUS => "plus",
+            MINUS => "minus",
+            STAR => "times",
+            SLASH => "divide",
+            PERCENT => "mod",
+            EQEQ | BANGEQ => "eq",
+            LT => "lt",
+            LTEQ => "le",
+            GT => "gt",
+            GTEQ => "ge",
+            _ => return None,
+        })
+    }
+
+    /// Calls `method` on `instance`. Unlike a call in Lox code, it's the
+    /// method's fault if it takes the wrong number of arguments.
+    fn call_method(
+        &mut self,
+        instance: &Arc<RwLock<LoxInstance>>,
+        method: &LoxFunction,
+        token: &Token,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let arity = method.arity();
+        if arity != arguments.len() {
+            let noun = if arguments.len() == 1 { "parameter" } else { "parameters" };
+            return Err(RuntimeError::new(method.name(), &format!(
+                "Method '{}' must take {} {}.", method.name().lexeme, arguments.len(), noun,
+            )));
+        }
+        method.bind(Value::Instance(Arc::clone(instance))).call(self, token, arguments)
+    }
+
+    fn binary(&mut self, operator: &Token, left: Value, right: Value) -> Result<Value, RuntimeError> {
+        if let (Value::Instance(instance), Some(name)) = (&left, Self::operator_method(operator.token_type)) {
+            let method = instance.read().unwrap().class.find_method(name);
+            if let Some(method) = method {
+                let result = self.call_method(instance, &method, operator, vec![right])?;
+                return Ok(match operator.token_type {
+                    EQEQ => Value::Bool(result.is_truthy()),
+                    BANGEQ => Value::Bool(!result.is_truthy()),
+                    _ => result,
+                });
+            }
+        }
+
+        match operator.token_type {
+            COMMA => return Ok(right),
+            EQEQ => return Ok(Value::Bool(left == right)),
+            BANGEQ => return Ok(Value::Bool(left != right)),
+            PLUS => {
+                match (&left, &right) {
+                    (Value::Number(l), Value::Number(r)) => return Ok(Value::Number(l + r)),
+                    (Value::Str(l), Value::Str(r)) => return Ok(Value::Str(format!("{}{}", l, r).into())),
+                    _ => return Err(RuntimeError::new(operator, "Operands must be two numbers or two strings.")),
+                }
+            }
+            _ => (),
+        }
+
+        let (l, r) = Self::number_operands(operator, &left, &right)?;
+        match operator.token_type {
+            MINUS => Ok(Value::Number(l - r)),
+            STAR => Ok(Value::Number(l * r)),
+            SLASH if r == 0.0 && !self.ieee_math => Err(RuntimeError::new(operator, "Division by zero.")),
+            SLASH => Ok(Value::Number(l / r)),
+            // The remainder of truncating division, as in C's fmod: it takes
+            // the sign of the left operand, so `-7 % 3` is -1 and `7 % -3` is 1.
+            PERCENT if r == 0.0 && !self.ieee_math => Err(RuntimeError::new(operator, "Modulo by zero.")),
+            PERCENT => Ok(Value::Number(l % r)),
+            AMP => Ok(Value::Number((Self::to_bits(l) & Self::to_bits(r)) as f64)),
+            PIPE => Ok(Value::Number((Self::to_bits(l) | Self::to_bits(r)) as f64)),
+            CARET => Ok(Value::Number((Self::to_bits(l) ^ Self::to_bits(r)) as f64)),
+            LTLT | GTGT => {
+                let shift = Self::to_bits(r);
+                if !(0..64).contains(&shift) {
+                    return Err(RuntimeError::new(operator, "Shift amount must be between 0 and 63."));
+                }
+                // Bits shifted past the top are lost; `>>` copies the sign bit.
+                let bits = Self::to_bits(l);
+                let shifted = if operator.token_type == LTLT { bits << shift } else { bits >> shift };
+                Ok(Value::Number(shifted as f64))
+            }
+            GT => Ok(Value::Bool(l > r)),
+            GTEQ => Ok(Value::Bool(l >= r)),
+            LT => Ok(Value::Bool(l < r)),
+            LTEQ => Ok(Value::Bool(l <= r)),
+            _ => unreachable!("not a binary operator: {:?}", operator.token_type),
+        }
+    }
+
+    //Helpers
+    /// Reads an assignable `target`, works out its new value with `update` and
+    /// stores that back, evaluating the target's object and index only once.
+    /// Returns the old and new values.
+    fn update_target(
+        &mut self,
+        target: &Expr,
+        update: impl FnOnce(&mut Self, Value) -> Result<Value, RuntimeError>,
+    ) -> Result<(Value, Value), RuntimeError> {
+        match target {
+            Expr::Variable { name, depth } => {
+                let old = self.look_up_variable(name, depth.get().copied())?;
+                let new = update(self, old.clone())?;
+                self.assign_variable(name, depth.get().copied(), new.clone())?;
+                Ok((old, new))
+            }
+            Expr::Get { object, name } => {
+                let object = self.evaluate(object)?;
+                let old = self.get_property(&object, name)?;
+                let new = update(self, old.clone())?;
+                Self::set_property(&object, name, new.clone())?;
+                Ok((old, new))
+            }
+            Expr::Index { object, bracket, index } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let old = Self::get_index(bracket, &object, &index)?;
+                let new = update(self, old.clone())?;
+                Self::set_index(bracket, &object, &index, new.clone())?;
+                Ok((old, new))
+            }
+            _ => unreachable!("the parser only allows assignable targets"),
+        }
+    }
+
+    fn look_up_variable(&self, name: &Token, depth: Option<usize>) -> Result<Value, RuntimeError> {
+        match depth {
+            Some(distance) => Environment::get_at(&self.environment, distance, name),
+            None => self.globals.read().unwrap().get(name),
+        }
+    }
+
+    fn assign_variable(&self, name: &Token, depth: Option<usize>, value: Value) -> Result<(), RuntimeError> {
+        match depth {
+            Some(distance) => Environment::assign_at(&self.environment, distance, name, value),
+            None => self.globals.write().unwrap().assign(name, value),
+        }
+    }
+
+    fn get_property(&mut self, object: &Value, name: &Token) -> Result<Value, RuntimeError> {
+        match object {
+            Value::Instance(instance) => LoxInstance::get(instance, self, name),
+            Value::Class(class) => LoxClass::get(class, self, name),
+            _ => Err(RuntimeError::new(name, "Only instances and classes have properties.")),
+        }
+    }
+
+    fn set_property(object: &Value, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        match object {
+            Value::Instance(instance) => instance.write().unwrap().set(name, value)?,
+            Value::Class(class) => class.set(name, value),
+            _ => return Err(RuntimeError::new(name, "Only instances and classes have fields.")),
+        }
+        Ok(())
+    }
+
+    fn get_index(bracket: &Token, object: &Value, index: &Value) -> Result<Value, RuntimeError> {
+        match object {
+            Value::Str(s) => {
+                let i = Self::sequence_index(bracket, index, s.chars().count(), "string")?;
+                Ok(Value::Str(s.chars().nth(i).unwrap().to_string().into()))
+            }
+            Value::List(list) => {
+                let list = list.read().unwrap();
+                let i = Self::sequence_index(bracket, index, list.len(), "list")?;
+                Ok(list[i].clone())
+            }
+            Value::Map(map) => {
+                let key = Self::map_key(bracket, index)?;
+                map.read().unwrap().get(&key).cloned().ok_or_else(|| {
+                    RuntimeError::new(bracket, &format!("Undefined key {}.", key.describe()))
+                })
+            }
+            _ => Err(RuntimeError::new(bracket, "Only strings, lists and maps can be indexed.")),
+        }
+    }
+
+    fn set_index(bracket: &Token, object: &Value, index: &Value, value: Value) -> Result<(), RuntimeError> {
+        match object {
+            Value::List(list) => {
+                let mut list = list.write().unwrap();
+                let i = Self::sequence_index(bracket, index, list.len(), "list")?;
+                list[i] = value;
+            }
+            Value::Map(map) => {
+                let key = Self::map_key(bracket, index)?;
+                map.write().unwrap().insert(key, value);
+            }
+            Value::Str(_) => return Err(RuntimeError::new(bracket, "Strings can't be modified.")),
+            _ => return Err(RuntimeError::new(bracket, "Only lists and maps can be indexed.")),
+        }
+        Ok(())
+    }
+
+    /// An index into a string or list of length `len`. Negative indices count
+    /// back from the end, so `-1` is the last element.
+    fn sequence_index(bracket: &Token, index: &Value, len: usize, kind: &str) -> Result<usize, RuntimeError> {
+        let n = Self::integer_index(bracket, index)?;
+        let i = if n < 0.0 { n + len as f64 } else { n };
+        if i < 0.0 || i >= len as f64 {
+            return Err(RuntimeError::new(bracket, &format!("Index {} out of bounds for {} of length {}.", n, kind, len)));
+        }
+        Ok(i as usize)
+    }
+
+    /// The bounds of `start..end` in a string or list of length `len`.
+    /// Missing bounds are the start and end of the whole thing, and negative
+    /// ones count back from the end, as with indices.
+    fn slice_bounds(
+        bracket: &Token,
+        start: Option<Value>,
+        end: Option<Value>,
+        len: usize,
+        kind: &str,
+    ) -> Result<(usize, usize), RuntimeError> {
+        let bound = |value: Option<Value>, default: usize| match value {
+            Some(value) => Self::integer_index(bracket, &value),
+            None => Ok(default as f64),
+        };
+        let (start, end) = (bound(start, 0)?, bound(end, len)?);
+        let resolve = |n: f64| if n < 0.0 { n + len as f64 } else { n };
+        let (from, to) = (resolve(start), resolve(end));
+
+        if from < 0.0 || to > len as f64 || from > to {
+            return Err(RuntimeError::new(bracket, &format!(
+                "Slice {}..{} out of bounds for {} of length {}.", start, end, kind, len,
+            )));
+        }
+        Ok((from as usize, to as usize))
+    }
+
+    fn integer_index(bracket: &Token, index: &Value) -> Result<f64, RuntimeError> {
+        let Value::Number(n) = *index else {
+            return Err(RuntimeError::new(bracket, "Index must be a number."));
+        };
+        if n.fract() != 0.0 {
+            return Err(RuntimeError::new(bracket, "Index must be an integer."));
+        }
+        Ok(n)
+    }
+
+    fn map_key(token: &Token, key: &Value) -> Result<MapKey, RuntimeError> {
+        MapKey::new(key).map_err(|m| RuntimeError::new(token, &m))
+    }
+
+    fn check_arity(paren: &Token, arity: usize, got: usize) -> Result<(), RuntimeError> {
+        if arity != got {
+            return Err(RuntimeError::new(paren, &format!("Expected {} arguments but got {}.", arity, got)));
+        }
+
+        Ok(())
+    }
+
+    fn number_operand(operator: &Token, operand: &Value) -> Result<f64, RuntimeError> {
+        match operand {
+            Value::Number(n) => Ok(*n),
+            _ => Err(RuntimeError::new(operator, "Operand must be a number.")),
+        }
+    }
+
+    /// The operand of a bitwise operator as a 64-bit integer: truncated
+    /// towards zero, with out-of-range values saturating at `i64::MIN` or
+    /// `i64::MAX` and NaN becoming 0. Results beyond 2^53 lose precision when
+    /// converted back to a number.
+    fn to_bits(n: f64) -> i64 {
+        n as i64
+    }
+
+    fn number_operands(operator: &Token, left: &Value, right: &Value) -> Result<(f64, f64), RuntimeError> {
+        match (left, right) {
+            (Value::Number(l), Value::Number(r)) => Ok((*l, *r)),
+            _ => Err(RuntimeError::new(operator, "Operands must be numbers.")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Frame;
+    use crate::parser::Parser;
+    use crate::resolver::Resolver;
+    use crate::scanner::Scanner;
+
+    fn eval(input: &str) -> Result<Value, RuntimeError> {
+        let mut scanner = Scanner::new(format!("{};", input));
+        let tokens = scanner.scan_tokens().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+        match &stmts[0] {
+            Stmt::Expression(expr) => Interpreter::new().evaluate(expr),
+            s => panic!("expected expression statement, got {:?}", s),
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(eval("1 + 2 * 3"), Ok(Value::Number(7.0)));
+        assert_eq!(eval("(1 + 2) * 3"), Ok(Value::Number(9.0)));
+        assert_eq!(eval("10 / 4 - -1"), Ok(Value::Number(3.5)));
+    }
+
+    #[test]
+    fn test_strings() {
+        assert_eq!(eval("\"foo\" + \"bar\""), Ok(Value::Str("foobar".into())));
+        assert_eq!(eval("\"a\" == \"a\""), Ok(Value::Bool(true)));
+
+        // Assigning and storing a string shares its text rather than copying it.
+        let interpreter = run("var a = \"shared\"; var b = a; var xs = [b]; var c = xs[0]; var d = \"sha\" + \"red\";").unwrap();
+        let (Value::Str(a), Value::Str(c)) = (get(&interpreter, "a"), get(&interpreter, "c")) else { panic!("expected strings") };
+        assert!(Arc::ptr_eq(&a, &c));
+        assert_eq!(get(&interpreter, "d"), Value::Str(a));
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(eval(r#""1 + 2 = ${1 + 2}""#), Ok(Value::Str("1 + 2 = 3".into())));
+        assert_eq!(eval(r#""${nil} ${true} ${"x${1.5}"}""#), Ok(Value::Str("nil true x1.5".into())));
+
+        let interp = run(r#"
+            var name = "world";
+            var greeting = "hello ${name}!";
+        "#).unwrap();
+        assert_eq!(get(&interp, "greeting"), Value::Str("hello world!".into()));
+    }
+
+    #[test]
+    fn test_comparison_and_equality() {
+        assert_eq!(eval("1 < 2"), Ok(Value::Bool(true)));
+        assert_eq!(eval("2 <= 1"), Ok(Value::Bool(false)));
+        assert_eq!(eval("1 == \"1\""), Ok(Value::Bool(false)));
+        assert_eq!(eval("nil == nil"), Ok(Value::Bool(true)));
+        assert_eq!(eval("nil != false"), Ok(Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_truthiness() {
+        assert_eq!(eval("!nil"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!false"), Ok(Value::Bool(true)));
+        assert_eq!(eval("!0"), Ok(Value::Bool(false)));
+        assert_eq!(eval("!\"\""), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_logical_operators_return_operands() {
+        assert_eq!(eval("nil or \"x\""), Ok(Value::Str("x".into())));
+        assert_eq!(eval("1 or 2"), Ok(Value::Number(1.0)));
+        assert_eq!(eval("nil and 1"), Ok(Value::Nil));
+        assert_eq!(eval("1 and 2"), Ok(Value::Number(2.0)));
+        assert_eq!(eval("false or nil"), Ok(Value::Nil));
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        // The right operands would raise runtime errors if evaluated.
+        assert_eq!(eval("true or -nil"), Ok(Value::Bool(true)));
+        assert_eq!(eval("false and undefined"), Ok(Value::Bool(false)));
+        assert!(eval("false or -nil").is_err());
+    }
+
+    #[test]
+    fn test_conditional() {
+        assert_eq!(eval("true ? 1 : 2"), Ok(Value::Number(1.0)));
+        assert_eq!(eval("nil ? 1 : 2"), Ok(Value::Number(2.0)));
+        assert_eq!(eval("1 == 2 ? \"a\" : 2 == 2 ? \"b\" : \"c\""), Ok(Value::Str("b".into())));
+        // The branch not taken would raise a runtime error if evaluated.
+        assert_eq!(eval("false ? -nil : 3"), Ok(Value::Number(3.0)));
+    }
+
+    #[test]
+    fn test_comma() {
+        assert_eq!(eval("1, 2, 3"), Ok(Value::Number(3.0)));
+        assert!(eval("-nil, 1").is_err());
+
+        let interpreter = run("var a = 0; var b = (a = 1, a + 1);").unwrap();
+        assert_eq!(get(&interpreter, "b"), Value::Number(2.0));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let interpreter = run("
+            var a = 10; a -= 4; a *= 3; a /= 2;
+            var s = \"ab\"; s += \"c\";
+            class Point {} var p = Point(); p.x = 1; p.x += 2; var px = p.x;
+            var xs = [1, 2]; xs[-1] *= 5;
+            var m = {\"n\": 1}; m[\"n\"] += 1;
+            var chained = a += 1;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "a"), Value::Number(10.0));
+        assert_eq!(get(&interpreter, "chained"), Value::Number(10.0));
+        assert_eq!(get(&interpreter, "s"), Value::Str("abc".into()));
+        assert_eq!(get(&interpreter, "px"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "xs").to_string(), "[1, 10]");
+        assert_eq!(get(&interpreter, "m").to_string(), "{\"n\": 2}");
+
+        // The target is only evaluated once.
+        let interpreter = run("var calls = 0; fun f() { calls++; return 0; } var xs = [5]; xs[f()] += 1;").unwrap();
+        assert_eq!(get(&interpreter, "calls"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "xs").to_string(), "[6]");
+        let interpreter = run("var calls = 0; class Box {} var box = Box(); box.n = 1; fun f() { calls++; return box; } f().n *= 3; var n = box.n;").unwrap();
+        assert_eq!(get(&interpreter, "calls"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "n"), Value::Number(3.0));
+
+        let err = run("var a = 1; a /= 0;").err().unwrap();
+        assert_eq!(err.message, "Division by zero.");
+        assert_eq!(err.token.lexeme, "/=");
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        let interpreter = run("
+            var i = 1;
+            var a = i++; var b = ++i; var c = i--; var d = --i;
+            class Counter {} var counter = Counter(); counter.n = 0; counter.n++; var n = counter.n;
+            var xs = [1, 2]; var old = xs[-1]++;
+            var total = 0; for (var j = 0; j < 3; j++) total += j;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "a"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "b"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "c"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "d"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "i"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "n"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "old"), Value::Number(2.0));
+        assert_eq!(get(&interpreter, "xs").to_string(), "[1, 3]");
+        assert_eq!(get(&interpreter, "total"), Value::Number(3.0));
+
+        // The target is only evaluated once.
+        let interpreter = run("var calls = 0; fun f() { calls++; return 0; } var xs = [5]; xs[f()]++;").unwrap();
+        assert_eq!(get(&interpreter, "calls"), Value::Number(1.0));
+
+        let err = run("var s = \"a\"; s++;").err().unwrap();
+        assert_eq!(err.message, "Operand must be a number.");
+        assert_eq!(err.token.lexeme, "++");
+    }
+
+    #[test]
+    fn test_runtime_errors() {
+        let err = eval("1 + \"a\"").unwrap_err();
+        assert_eq!(err.to_string(), "Operands must be two numbers or two strings.\n[line 1]");
+
+        let err = eval("-\"a\"").unwrap_err();
+        assert_eq!(err.message, "Operand must be a number.");
+
+        let err = eval("true < 1").unwrap_err();
+        assert_eq!(err.message, "Operands must be numbers.");
+    }
+
+    fn run(input: &str) -> Result<Interpreter, RuntimeError> {
+        let mut scanner = Scanner::new(input.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+        let stmts = Parser::new(tokens).parse().unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts);
+        assert!(resolver.errors.is_empty(), "{:?}", resolver.errors);
+        let mut interpreter = Interpreter::new();
+        interpreter.interpret(&stmts)?;
+        Ok(interpreter)
+    }
+
+    fn get(interpreter: &Interpreter, name: &str) -> Value {
+        interpreter.globals.read().unwrap().get(&Token::new(IDENT, name, 1)).unwrap()
+    }
+
+    #[test]
+    fn test_variables() {
+        let interpreter = run("
+        var a = 1;
+        var b;
+        var c = a = a + 1;
+        b = c * 10;
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "a"), Value::Number(2.0));
+        assert_eq!(get(&interpreter, "b"), Value::Number(20.0));
+        assert_eq!(get(&interpreter, "c"), Value::Number(2.0));
+
+        let err = run("print x;").err().unwrap();
+        assert_eq!(err.to_string(), "Undefined variable 'x'.\n[line 1]");
+
+        let err = run("y = 1;").err().unwrap();
+        assert_eq!(err.message, "Undefined variable 'y'.");
+    }
+
+    #[test]
+    fn test_block_scoping() {
+        let interpreter = run("
+        var a = \"global a\";
+        var b = \"global b\";
+        var inner;
+        {
+            var a = \"outer a\";
+            {
+                var a = \"inner a\";
+                inner = a + b;
+            }
+            b = a;
+        }
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "inner"), Value::Str("inner aglobal b".into()));
+        assert_eq!(get(&interpreter, "a"), Value::Str("global a".into()));
+        assert_eq!(get(&interpreter, "b"), Value::Str("outer a".into()));
+
+        let err = run("{ var scoped = 1; } print scoped;").err().unwrap();
+        assert_eq!(err.message, "Undefined variable 'scoped'.");
+    }
+
+    #[test]
+    fn test_scope_restored_after_error() {
+        let mut scanner = Scanner::new("{ var a = 1; a + nil; }".to_string());
+        let stmts = Parser::new(scanner.scan_tokens().unwrap()).parse().unwrap();
+        let mut interpreter = Interpreter::new();
+        interpreter.globals.write().unwrap().define("a", Value::Number(0.0));
+
+        assert!(interpreter.interpret(&stmts).is_err());
+        assert_eq!(get(&interpreter, "a"), Value::Number(0.0));
+    }
+
+    #[test]
+    fn test_if_else() {
+        let interpreter = run("
+        var a; var b; var c;
+        if (1 < 2) a = \"then\"; else a = \"else\";
+        if (nil) b = \"then\"; else b = \"else\";
+        if (false) c = 1;
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "a"), Value::Str("then".into()));
+        assert_eq!(get(&interpreter, "b"), Value::Str("else".into()));
+        assert_eq!(get(&interpreter, "c"), Value::Nil);
+    }
+
+    #[test]
+    fn test_loops() {
+        let interpreter = run("
+        var sum = 0;
+        var i = 0;
+        while (i < 5) { sum = sum + i; i = i + 1; }
+
+        var fact = 1;
+        for (var n = 1; n <= 5; n = n + 1) fact = fact * n;
+
+        var j = 0;
+        for (; j < 3;) j = j + 1;
+
+        var runs = 0;
+        do runs = runs + 1; while (false);
+        var k = 0;
+        do { k = k + 1; } while (k < 4);
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "runs"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "k"), Value::Number(4.0));
+
+        assert_eq!(get(&interpreter, "sum"), Value::Number(10.0));
+        assert_eq!(get(&interpreter, "fact"), Value::Number(120.0));
+        assert_eq!(get(&interpreter, "j"), Value::Number(3.0));
+
+        let err = run("for (var n = 0; n < 1; n = n + 1) {} print n;").err().unwrap();
+        assert_eq!(err.message, "Undefined variable 'n'.");
+    }
+
+    #[test]
+    fn test_multiple_var_declarations() {
+        let interpreter = run("
+            var a = 1, b = a + 1, c;
+            fun pair() { return [\"x\", \"y\"]; }
+            var [first, second] = pair();
+            var swapped;
+            {
+                var [p, q] = [1, 2];
+                var [q2, p2] = [q, p];
+                swapped = [p2, q2];
+            }
+        ").unwrap();
+        assert_eq!(get(&interpreter, "b"), Value::Number(2.0));
+        assert_eq!(get(&interpreter, "c"), Value::Nil);
+        assert_eq!(get(&interpreter, "first"), Value::Str("x".into()));
+        assert_eq!(get(&interpreter, "second"), Value::Str("y".into()));
+        assert_eq!(get(&interpreter, "swapped").to_string(), "[1, 2]");
+
+        let message = |input: &str| run(input).err().unwrap().message;
+        assert_eq!(message("var [a, b] = [1];"), "Expected a list of length 2 but got one of length 1.");
+        assert_eq!(message("var [a] = \"a\";"), "Only lists can be destructured.");
+    }
+
+    #[test]
+    fn test_functions() {
+        let interpreter = run("
+        fun add(a, b) { return a + b; }
+        fun noReturn() { var x = 1; }
+        fun early(n) {
+            while (true) {
+                if (n > 3) return \"big\";
+                return \"small\";
+            }
+        }
+        fun fib(n) {
+            if (n < 2) return n;
+            return fib(n - 1) + fib(n - 2);
+        }
+        var sum = add(1, 2);
+        var nothing = noReturn();
+        var big = early(5);
+        var small = early(1);
+        var f = fib(10);
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "sum"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "nothing"), Value::Nil);
+        assert_eq!(get(&interpreter, "big"), Value::Str("big".into()));
+        assert_eq!(get(&interpreter, "small"), Value::Str("small".into()));
+        assert_eq!(get(&interpreter, "f"), Value::Number(55.0));
+        assert_eq!(get(&interpreter, "add").to_string(), "<fn add>");
+    }
+
+    #[test]
+    fn test_closures() {
+        let interpreter = run("
+        fun makeCounter() {
+            var i = 0;
+            fun count() {
+                i = i + 1;
+                return i;
+            }
+            return count;
+        }
+
+        var counter = makeCounter();
+        counter();
+        var second = counter();
+        var other = makeCounter()();
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "second"), Value::Number(2.0));
+        assert_eq!(get(&interpreter, "other"), Value::Number(1.0));
+    }
+
+    #[test]
+    fn test_anonymous_functions() {
+        let interpreter = run("
+        fun thrice(f) {
+            for (var i = 1; i <= 3; i = i + 1) f(i);
+        }
+
+        var sum = 0;
+        thrice(fun (i) { sum = sum + i; });
+        var twice = fun (x) { return x * 2; }(21);
+        var f = fun () {};
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "sum"), Value::Number(6.0));
+        assert_eq!(get(&interpreter, "twice"), Value::Number(42.0));
+        assert_eq!(get(&interpreter, "f").to_string(), "<fn>");
+
+        let err = run("var f = fun () { -nil; };\nf();").err().unwrap();
+        assert_eq!(err.to_string(), "Operand must be a number.\n[line 1] in fun()\n[line 2] in script");
+    }
+
+    #[test]
+    fn test_captured_variables_outlive_their_scope() {
+        let interpreter = run("
+        var getter;
+        var setter;
+        {
+            var shared = \"before\";
+            fun get() { return shared; }
+            fun set(v) { shared = v; }
+            getter = get;
+            setter = set;
+        }
+        setter(\"after\");
+        var result = getter();
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "result"), Value::Str("after".into()));
+    }
+
+    #[test]
+    fn test_closures_bind_to_declaration_in_scope() {
+        let interpreter = run("
+        var a = \"global\";
+        var first;
+        var second;
+        {
+            fun showA() { return a; }
+            first = showA();
+            var a = \"block\";
+            second = showA();
+        }
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "first"), Value::Str("global".into()));
+        assert_eq!(get(&interpreter, "second"), Value::Str("global".into()));
+    }
+
+    #[test]
+    fn test_classes() {
+        let interpreter = run("
+        class Point {
+            init(x, y) {
+                this.x = x;
+                this.y = y;
+            }
+
+            sum() { return this.x + this.y; }
+
+            scale(n) {
+                this.x = this.x * n;
+                this.y = this.y * n;
+                return this;
+            }
+        }
+
+        var p = Point(1, 2);
+        var sum = p.scale(10).sum();
+        var method = p.sum;
+        p.x = 0;
+        var bound = method();
+        var reinit = p.init(3, 4);
+        var same = reinit == p;
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "sum"), Value::Number(30.0));
+        assert_eq!(get(&interpreter, "bound"), Value::Number(20.0));
+        assert_eq!(get(&interpreter, "same"), Value::Bool(true));
+        assert_eq!(get(&interpreter, "Point").to_string(), "Point");
+        assert_eq!(get(&interpreter, "p").to_string(), "Point instance");
+    }
+
+    #[test]
+    fn test_fields_shadow_methods_and_init_returns_this() {
+        let interpreter = run("
+        class A {
+            init() { this.ready = true; return; }
+            m() { return \"method\"; }
+        }
+        var a = A();
+        a.m = \"field\";
+        var m = a.m;
+        var ready = a.ready;
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "m"), Value::Str("field".into()));
+        assert_eq!(get(&interpreter, "ready"), Value::Bool(true));
+    }
+
+    #[test]
+    fn test_class_methods_and_properties() {
+        let interpreter = run("
+            class Math {
+                class square(n) { return n * n; }
+                class cube(n) { return this.square(n) * n; }
+            }
+            var nine = Math.square(3);
+            var eight = Math.cube(2);
+
+            class Counter {
+                init() { Counter.count = Counter.count + 1; }
+                class reset() { this.count = 0; }
+            }
+            Counter.reset();
+            Counter(); Counter();
+            Counter.count++;
+            var count = Counter.count;
+
+            class Base { class create() { return this(); } class name() { return \"base\"; } }
+            class Derived < Base { class name() { return \"derived \" + super.name(); } }
+            Base.shared = 1;
+            var created = Derived.create();
+            var derivedName = Derived.name();
+            var inherited = Derived.shared;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "nine"), Value::Number(9.0));
+        assert_eq!(get(&interpreter, "eight"), Value::Number(8.0));
+        assert_eq!(get(&interpreter, "count"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "created").to_string(), "Derived instance");
+        assert_eq!(get(&interpreter, "derivedName"), Value::Str("derived base".into()));
+        assert_eq!(get(&interpreter, "inherited"), Value::Number(1.0));
+
+        // Class methods aren't callable on instances, nor methods on the class.
+        let message = |input: &str| run(input).err().unwrap().message;
+        assert_eq!(message("class A { class m() {} } A().m();"), "Undefined property 'm'.");
+        assert_eq!(message("class A { m() {} } A.m();"), "Undefined property 'm'.");
+    }
+
+    #[test]
+    fn test_getters() {
+        let interpreter = run("
+            class Circle {
+                init(r) { this.r = r; }
+                area { return 3 * this.r * this.r; }
+                class unit { return Circle(1); }
+            }
+            class Ring < Circle {
+                area { return super.area - 3; }
+            }
+            var area = Circle(2).area;
+            var unitArea = Circle.unit.area;
+            var ringArea = Ring(2).area;
+
+            var c = Circle(1);
+            c.area = 5;
+            var shadowed = c.area;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "area"), Value::Number(12.0));
+        assert_eq!(get(&interpreter, "unitArea"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "ringArea"), Value::Number(9.0));
+        assert_eq!(get(&interpreter, "shadowed"), Value::Number(5.0));
+
+        let err = run("class A {\n  broken { return -nil; }\n}\nA().broken;").err().unwrap();
+        assert_eq!(err.message, "Operand must be a number.");
+        assert_eq!(err.trace[0].function, "broken");
+        assert_eq!(err.trace[0].line, 4);
+    }
+
+    #[test]
+    fn test_operator_overloading() {
+        let interpreter = run("
+            class Vec {
+                init(x, y) { this.x = x; this.y = y; }
+                plus(other) { return Vec(this.x + other.x, this.y + other.y); }
+                times(k) { return Vec(this.x * k, this.y * k); }
+                eq(other) { return this.x == other.x and this.y == other.y; }
+                lt(other) { return this.x < other.x; }
+                str() { return \"(${this.x}, ${this.y})\"; }
+            }
+            var v = Vec(1, 2) + Vec(3, 4) * 2;
+            var shown = \"v = ${v}\";
+            var xs = [Vec(0, 1), \"s\"];
+            push(xs, xs);
+            var nested = \"${xs} ${ {\"k\": [Vec(2, 3)]} }\";
+            var same = Vec(1, 2) == Vec(1, 2);
+            var different = Vec(1, 2) != Vec(1, 2);
+            var less = Vec(1, 0) < Vec(2, 0);
+            v += Vec(1, 1);
+            var x = v.x;
+
+            class Plain {}
+            var p = Plain();
+            var identity = p == p;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "shown"), Value::Str("v = (7, 10)".into()));
+        assert_eq!(get(&interpreter, "nested"), Value::Str("[(0, 1), \"s\", [...]] {\"k\": [(2, 3)]}".into()));
+        assert_eq!(get(&interpreter, "same"), Value::Bool(true));
+        assert_eq!(get(&interpreter, "different"), Value::Bool(false));
+        assert_eq!(get(&interpreter, "less"), Value::Bool(true));
+        assert_eq!(get(&interpreter, "x"), Value::Number(8.0));
+        assert_eq!(get(&interpreter, "identity"), Value::Bool(true));
+
+        let message = |input: &str| run(input).err().unwrap().message;
+        assert_eq!(message("class A { plus() {} } A() + 1;"), "Method 'plus' must take 1 parameter.");
+        assert_eq!(message("class A { str() { return 1; } } print A();"), "Method 'str' must return a string.");
+        assert_eq!(message("class A { str() { return 1; } } print [A()];"), "Method 'str' must return a string.");
+        assert_eq!(message("class A {} A() - 1;"), "Operands must be numbers.");
+    }
+
+    #[test]
+    fn test_declared_fields() {
+        let interpreter = run("
+            class Point {
+                var x, y;
+                init(x) { this.x = x; }
+            }
+            class Point3 < Point { var z; }
+            var p = Point3(1);
+            p.y = 2;
+            p.z = 3;
+            var sum = p.x + p.y + p.z;
+
+            class Loose {}
+            var l = Loose();
+            l.anything = 1;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "sum"), Value::Number(6.0));
+
+        let message = |input: &str| run(input).err().unwrap().message;
+        assert_eq!(
+            message("class P { var x; init() { this.widht = 1; } } P();"),
+            "Undeclared field 'widht' on 'P'.",
+        );
+        assert_eq!(message("class P { var x; } class Q < P {} Q().y = 1;"), "Undeclared field 'y' on 'Q'.");
+        assert_eq!(message("class P { var x; } print P().x;"), "Field 'x' was read before it was set.");
+        assert_eq!(message("class P { var x; } print P().y;"), "Undefined property 'y'.");
+    }
+
+    #[test]
+    fn test_traits() {
+        let interpreter = run("
+            trait Named {
+                greeting { return \"I am \" + this.name(); }
+                name() { return \"nobody\"; }
+            }
+            trait Counted {
+                count() { this.n = this.n + 1; return this.n; }
+            }
+            class Base { init() { this.n = 0; } }
+            class Thing < Base with Named, Counted {
+                name() { return \"thing\"; }
+            }
+            var thing = Thing();
+            var greeting = thing.greeting;
+            thing.count();
+            var count = thing.count();
+            var shown = \"${Named}\";
+        ").unwrap();
+        assert_eq!(get(&interpreter, "greeting"), Value::Str("I am thing".into()));
+        assert_eq!(get(&interpreter, "count"), Value::Number(2.0));
+        assert_eq!(get(&interpreter, "shown"), Value::Str("Named trait".into()));
+
+        // A mixed-in `init` is an initializer, so an early `return;` still
+        // gives back the instance.
+        let interpreter = run("
+            trait Init { init(n) { this.n = n; if (n > 0) return; this.n = -1; } }
+            class C with Init {}
+            var c = C(2);
+            var again = c.init(0);
+            var same = again == c;
+        ").unwrap();
+        assert_eq!(get(&interpreter, "same"), Value::Bool(true));
+
+        let message = |input: &str| run(input).err().unwrap().message;
+        assert_eq!(message("class A {} class B with A {}"), "Only traits can be mixed in.");
+
+        // Traits only known at runtime are still checked for conflicts.
+        let mut interpreter = Interpreter::new();
+        let mut run_line = |input: &str| {
+            let stmts = Parser::new(Scanner::new(input.to_string()).scan_tokens().unwrap()).parse().unwrap();
+            Resolver::new().resolve(&stmts);
+            interpreter.interpret(&stmts)
+        };
+        run_line("trait A { m() {} } trait B { m() {} }").unwrap();
+        let err = run_line("class C with A, B {}").unwrap_err();
+        assert_eq!(err.message, "Method 'm' is provided by both 'A' and 'B'.");
+        assert_eq!(err.token.lexeme, "C");
+    }
+
+    #[test]
+    fn test_class_errors() {
+        let err = run("class A {} A().missing;").err().unwrap();
+        assert_eq!(err.message, "Undefined property 'missing'.");
+
+        let err = run("var s = \"str\"; s.len;").err().unwrap();
+        assert_eq!(err.message, "Only instances and classes have properties.");
+
+        let err = run("1 .x = 2;").err().unwrap();
+        assert_eq!(err.message, "Only instances and classes have fields.");
+
+        let err = run("class A { init(a) {} } A();").err().unwrap();
+        assert_eq!(err.message, "Expected 1 arguments but got 0.");
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let interpreter = run("
+        class Animal {
+            init(name) { this.name = name; }
+            speak() { return this.name + \" makes a sound\"; }
+            kind() { return \"animal\"; }
+        }
+
+        class Dog < Animal {
+            speak() { return super.speak() + \" (woof)\"; }
+        }
+
+        class Puppy < Dog {
+            speak() { return super.speak() + \" (yip)\"; }
+        }
+
+        var d = Dog(\"Rex\");
+        var speech = d.speak();
+        var inherited = d.kind();
+        var puppy = Puppy(\"Bit\").speak();
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "speech"), Value::Str("Rex makes a sound (woof)".into()));
+        assert_eq!(get(&interpreter, "inherited"), Value::Str("animal".into()));
+        assert_eq!(get(&interpreter, "puppy"), Value::Str("Bit makes a sound (woof) (yip)".into()));
+    }
+
+    #[test]
+    fn test_inheritance_errors() {
+        let err = run("var NotAClass = 1; class A < NotAClass {}").err().unwrap();
+        assert_eq!(err.message, "Superclass must be a class.");
+
+        let err = run("class A {} class B < A { m() { return super.missing; } } B().m();").err().unwrap();
+        assert_eq!(err.message, "Undefined property 'missing'.");
+    }
+
+    #[test]
+    fn test_call_errors() {
+        let err = run("fun f(a) {} f(1, 2);").err().unwrap();
+        assert_eq!(err.message, "Expected 1 arguments but got 2.");
+
+        let err = run("\"not a function\"();").err().unwrap();
+        assert_eq!(err.message, "Can only call functions and classes.");
+
+        let err = run("fun f() { return g; } f();").err().unwrap();
+        assert_eq!(err.message, "Undefined variable 'g'.");
+    }
+
+    #[test]
+    fn test_clock() {
+        let interpreter = run("var a = clock(); var b = clock(); var elapsed = b - a;").unwrap();
+        match get(&interpreter, "a") {
+            Value::Number(n) => assert!(n > 0.0),
+            v => panic!("expected a number, got {:?}", v),
+        }
+        assert_eq!(get(&interpreter, "clock").to_string(), "<native fn clock>");
+
+        let err = run("clock(1);").err().unwrap();
+        assert_eq!(err.message, "Expected 0 arguments but got 1.");
+    }
+
+    #[test]
+    fn test_string_natives() {
+        assert_eq!(eval("len(\"héllo\")"), Ok(Value::Number(5.0)));
+        assert_eq!(eval("len(\"\")"), Ok(Value::Number(0.0)));
+        assert_eq!(eval("substr(\"héllo\", 1, 3)"), Ok(Value::Str("éll".into())));
+        assert_eq!(eval("substr(\"abc\", 3, 0)"), Ok(Value::Str("".into())));
+        assert_eq!(eval("upper(\"straße\")"), Ok(Value::Str("STRASSE".into())));
+        assert_eq!(eval("lower(\"ÀB\")"), Ok(Value::Str("àb".into())));
+        assert_eq!(eval("contains(\"haystack\", \"st\")"), Ok(Value::Bool(true)));
+        assert_eq!(eval("contains(\"haystack\", \"needle\")"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_string_native_errors() {
+        let message = |input: &str| eval(input).unwrap_err().message;
+
+        assert_eq!(message("upper(1)"), "Expected a string.");
+        assert_eq!(message("substr(\"abc\", 2, 2)"), "Substring 2..4 out of bounds for string of length 3.");
+        assert_eq!(message("substr(\"abc\", 4, 0)"), "Substring 4..4 out of bounds for string of length 3.");
+        assert_eq!(message("substr(\"abc\", -1, 1)"), "Expected a non-negative integer.");
+        assert_eq!(message("substr(\"abc\", 0.5, 1)"), "Expected a non-negative integer.");
+        assert_eq!(message("contains(\"abc\", nil)"), "Expected a string.");
+    }
+
+    #[test]
+    fn test_lists() {
+        let interpreter = run("
+        var xs = [1, \"two\", nil];
+        var first = xs[0];
+        xs[2] = [3];
+        var alias = xs;
+        push(alias, 4);
+        var popped = pop(xs);
+        var length = len(xs);
+        var words = split(\"a,b,,c\", \",\");
+        var chars = split(\"hé\", \"\");
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "first"), Value::Number(1.0));
+        assert_eq!(get(&interpreter, "popped"), Value::Number(4.0));
+        assert_eq!(get(&interpreter, "length"), Value::Number(3.0));
+        assert_eq!(get(&interpreter, "xs").to_string(), "[1, \"two\", [3]]");
+        assert_eq!(get(&interpreter, "xs"), get(&interpreter, "alias"));
+        assert_eq!(get(&interpreter, "words").to_string(), "[\"a\", \"b\", \"\", \"c\"]");
+        assert_eq!(get(&interpreter, "chars").to_string(), "[\"h\", \"é\"]");
+        assert_eq!(eval("[1] == [1]"), Ok(Value::Bool(false)));
+
+        let interpreter = run("var xs = [1]; push(xs, xs);").unwrap();
+        assert_eq!(get(&interpreter, "xs").to_string(), "[1, [...]]");
+    }
+
+    #[test]
+    fn test_list_errors() {
+        let message = |input: &str| eval(input).unwrap_err().message;
+
+        assert_eq!(message("[1, 2][2]"), "Index 2 out of bounds for list of length 2.");
+        assert_eq!(message("[1, 2][-3]"), "Index -3 out of bounds for list of length 2.");
+        assert_eq!(message("[1][0.5]"), "Index must be an integer.");
+        assert_eq!(message("[1][\"0\"]"), "Index must be a number.");
+        assert_eq!(message("nil[0]"), "Only strings, lists and maps can be indexed.");
+        assert_eq!(message("nil[0] = 1"), "Only lists and maps can be indexed.");
+        assert_eq!(message("[][0] = 1"), "Index 0 out of bounds for list of length 0.");
+        assert_eq!(message("pop([])"), "Can't pop from an empty list.");
+        assert_eq!(message("push(1, 2)"), "Expected a list.");
+        assert_eq!(message("len(nil)"), "Expected a string, a list or a map.");
+
+        let err = eval("[1][\n5]").unwrap_err();
+        assert_eq!(err.token.line, 2);
+    }
+
+    #[test]
+    fn test_indexing_and_slicing() {
+        let show = |input: &str| eval(input).unwrap().to_string();
+
+        assert_eq!(show("\"héllo\"[1]"), "é");
+        assert_eq!(show("\"hello\"[-1]"), "o");
+        assert_eq!(show("[1, 2, 3][-3]"), "1");
+        assert_eq!(show("\"hello\"[1..3]"), "el");
+        assert_eq!(show("\"hello\"[..-1]"), "hell");
+        assert_eq!(show("\"hello\"[-3..]"), "llo");
+        assert_eq!(show("\"hello\"[2..2]"), "");
+        assert_eq!(show("[1, 2, 3, 4][1..3]"), "[2, 3]");
+        assert_eq!(show("[1, 2, 3][..]"), "[1, 2, 3]");
+
+        let interpreter = run("var xs = [1, 2]; var ys = xs[..]; push(ys, 3); xs[-1] = 5;").unwrap();
+        assert_eq!(get(&interpreter, "xs").to_string(), "[1, 5]");
+        assert_eq!(get(&interpreter, "ys").to_string(), "[1, 2, 3]");
+    }
+
+    #[test]
+    fn test_indexing_and_slicing_errors() {
+        let message = |input: &str| eval(input).unwrap_err().message;
+
+        assert_eq!(message("\"abc\"[3]"), "Index 3 out of bounds for string of length 3.");
+        assert_eq!(message("\"abc\"[-4]"), "Index -4 out of bounds for string of length 3.");
+        assert_eq!(message("\"abc\"[1..4]"), "Slice 1..4 out of bounds for string of length 3.");
+        assert_eq!(message("[1, 2][-3..]"), "Slice -3..2 out of bounds for list of length 2.");
+        assert_eq!(message("[1, 2][2..1]"), "Slice 2..1 out of bounds for list of length 2.");
+        assert_eq!(message("\"abc\"[0..0.5]"), "Index must be an integer.");
+        assert_eq!(message("\"abc\"[0] = \"x\""), "Strings can't be modified.");
+        assert_eq!(message("({})[0..1]"), "Only strings and lists can be sliced.");
+
+        let err = eval("\"abc\"\n[\n1..9]").unwrap_err();
+        assert_eq!(err.token.line, 3);
+    }
+
+    #[test]
+    fn test_maps() {
+        let interpreter = run("
+        var m = {\"b\": 1, 2: [], nil: true};
+        m[\"a\"] = m[\"b\"] + 1;
+        m[\"b\"] = 3;
+        m[-0] = \"zero\";
+        var zero = m[0];
+        var removed = remove(m, nil);
+        var keys = keys(m);
+        var values = values(m);
+        var size = len(m);
+        var has = contains(m, \"a\");
+        var lacks = contains(m, \"z\");
+        ").unwrap();
+
+        assert_eq!(get(&interpreter, "zero"), Value::Str("zero".into()));
+        assert_eq!(get(&interpreter, "removed"), Value::Bool(true));
+        assert_eq!(get(&interpreter, "keys").to_string(), "[\"b\", 2, \"a\", 0]");
+        assert_eq!(get(&interpreter, "values").to_string(), "[3, [], 2, \"zero\"]");
+        assert_eq!(get(&interpreter, "size"), Value::Number(4.0));
+        assert_eq!(get(&interpreter, "has"), Value::Bool(true));
+        assert_eq!(get(&interpreter, "lacks"), Value::Bool(false));
+        assert_eq!(get(&interpreter, "m").to_string(), "{\"b\": 3, 2: [], \"a\": 2, 0: \"zero\"}");
+        assert_eq!(eval("({}) == {}"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_map_errors() {
+        let message = |input: &str| eval(input).unwrap_err().message;
+
+        assert_eq!(message("({\"a\": 1})[\"b\"]"), "Undefined key \"b\".");
+        assert_eq!(message("({[]: 1})"), "Map keys must be strings, numbers, booleans or nil.");
+        assert_eq!(message("({})[{}] = 1"), "Map keys must be strings, numbers, booleans or nil.");
+        assert_eq!(message("remove({}, 1)"), "Undefined key 1.");
+        assert_eq!(message("keys([])"), "Expected a map.");
+    }
+
+    #[test]
+    fn test_math_natives() {
+        assert_eq!(eval("floor(-1.5)"), Ok(Value::Number(-2.0)));
+        assert_eq!(eval("ceil(-1.5)"), Ok(Value::Number(-1.0)));
+        assert_eq!(eval("abs(-3)"), Ok(Value::Number(3.0)));
+        assert_eq!(eval("sqrt(16)"), Ok(Value::Number(4.0)));
+        assert_eq!(eval("pow(2, 10)"), Ok(Value::Number(1024.0)));
+        assert_eq!(eval("pow(10, 400)"), Ok(Value::Number(f64::INFINITY)));
+        assert_eq!(eval("min(1, -1)"), Ok(Value::Number(-1.0)));
+        assert_eq!(eval("max(1, -1)"), Ok(Value::Number(1.0)));
+
+        assert_eq!(eval("sqrt(-1)").unwrap_err().message, "Can't take the square root of a negative number.");
+        assert_eq!(eval("floor(\"1\")").unwrap_err().message, "Expected a number.");
+    }
+
+    #[test]
+    fn test_random() {
+        let interpreter = run("var a = random(); var b = random();").unwrap();
+        let (Value::Number(a), Value::Number(b)) = (get(&interpreter, "a"), get(&interpreter, "b")) else {
+            panic!("expected numbers");
+        };
+        assert!((0.0..1.0).contains(&a) && (0.0..1.0).contains(&b));
+
+        // The same seed always gives the same sequence.
+        let random = native::new_random(42);
+        let draws: Vec<Value> = (0..3).map(|_| random(&[]).unwrap()).collect();
+        assert_eq!(draws, vec![
+            Value::Number(2.522779873892489e-9),
+            Value::Number(0.6876666536445664),
+            Value::Number(0.06270967138638428),
+        ]);
+    }
+
+    #[test]
+    fn test_sleep() {
+        let interpreter = run("var a = clock(); var result = sleep(20); var elapsed = clock() - a;").unwrap();
+        assert_eq!(get(&interpreter, "result"), Value::Nil);
+        match get(&interpreter, "elapsed") {
+            Value::Number(n) => assert!(n >= 0.02, "slept for {}s", n),
+            v => panic!("expected a number, got {:?}", v),
+        }
+
+        let err = run("sleep(-1);").err().unwrap();
+        assert_eq!(err.message, "Expected a non-negative number of milliseconds.");
+        let err = run("sleep(1e300);").err().unwrap();
+        assert_eq!(err.message, "Sleep duration is too long.");
+        assert!(run("sleep(\"1\");").is_err());
+    }
+
+    #[test]
+    fn test_stack_traces() {
+        let err = run("fun a() {
+            b();
+        }
+        fun b() {
+            return -nil;
+        }
+        a();").err().unwrap();
+
+        assert_eq!(err.trace, vec![
+            Frame { function: "b".to_string(), line: 2 },
+            Frame { function: "a".to_string(), line: 7 },
+        ]);
+        assert_eq!(err.to_string(), "Operand must be a number.
+[line 5] in b()
+[line 2] in a()
+[line 7] in script");
+
+        let err = run("class A { init() { this.x = -nil; } }\nA();").err().unwrap();
+        assert_eq!(err.to_string(), "Operand must be a number.\n[line 1] in init()\n[line 2] in script");
+
+        let err = run("-nil;").err().unwrap();
+        assert_eq!(err.to_string(), "Operand must be a number.\n[line 1]");
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Value::Number(3.0).to_string(), "3");
+        assert_eq!(Value::Number(2.5).to_string(), "2.5");
+        assert_eq!(Value::Number(-0.0).to_string(), "-0");
+        assert_eq!(Value::Number(1e21).to_string(), "1000000000000000000000");
+        assert_eq!(Value::Nil.to_string(), "nil");
+        assert_eq!(Value::Bool(true).to_string(), "true");
+        assert_eq!(Value::Str("a\"b".into()).to_string(), "a\"b");
+
+        assert_eq!(Value::Number(f64::INFINITY).to_string(), "Infinity");
+        assert_eq!(Value::Number(f64::NEG_INFINITY).to_string(), "-Infinity");
+        assert_eq!(Value::Number(f64::NAN).to_string(), "NaN");
+    }
+
+    #[test]
+    fn test_division_by_zero() {
+        let err = eval("1 +\n 2 / 0").unwrap_err();
+        assert_eq!(err.to_string(), "Division by zero.\n[line 2]");
+        assert!(eval("0 / -0").is_err());
+        assert_eq!(eval("0 / 2"), Ok(Value::Number(0.0)));
+
+        let mut interpreter = Interpreter::new();
+        interpreter.ieee_math = true;
+        let mut ieee = |input: &str| {
+            let stmts = Parser::new(Scanner::new(format!("{};", input)).scan_tokens().unwrap()).parse().unwrap();
+            interpreter.interpret(&stmts).unwrap().to_string()
+        };
+        assert_eq!(ieee("1 / 0"), "Infinity");
+        assert_eq!(ieee("-1 / 0"), "-Infinity");
+        assert_eq!(ieee("0 / 0"), "NaN");
+        assert_eq!(ieee("1 % 0"), "NaN");
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let number = |input: &str| match eval(input) {
+            Ok(Value::Number(n)) => n,
+            result => panic!("expected a number, got {:?}", result),
+        };
+
+        assert_eq!(number("12 & 10"), 8.0);
+        assert_eq!(number("12 | 10"), 14.0);
+        assert_eq!(number("12 ^ 10"), 6.0);
+        assert_eq!(number("~5"), -6.0);
+        assert_eq!(number("1 << 4"), 16.0);
+        assert_eq!(number("-16 >> 2"), -4.0);
+        assert_eq!(number("7.9 & -1"), 7.0);
+        assert_eq!(number("1 << 63"), i64::MIN as f64);
+        assert_eq!(number("3 << 62"), (i64::MIN + (1 << 62)) as f64);
+        assert_eq!(number("pow(10, 300) | 0"), i64::MAX as f64);
+        assert_eq!(number("1 | 2 ^ 3 & 4 << 1"), 3.0);
+        assert_eq!(eval("5 & 1 == 1"), Ok(Value::Bool(true)));
+
+        let message = |input: &str| eval(input).unwrap_err().message;
+        assert_eq!(message("1 << 64"), "Shift amount must be between 0 and 63.");
+        assert_eq!(message("1 >> -1"), "Shift amount must be between 0 and 63.");
+        assert_eq!(message("~nil"), "Operand must be a number.");
+        assert_eq!(message("true & 1"), "Operands must be numbers.");
+    }
+
+    #[test]
+    fn test_modulo() {
+        assert_eq!(eval("7 % 3"), Ok(Value::Number(1.0)));
+        assert_eq!(eval("-7 % 3"), Ok(Value::Number(-1.0)));
+        assert_eq!(eval("7 % -3"), Ok(Value::Number(1.0)));
+        assert_eq!(eval("5.5 % 2"), Ok(Value::Number(1.5)));
+        assert_eq!(eval("1 + 10 % 4 * 2"), Ok(Value::Number(5.0)));
+
+        assert_eq!(eval("1 % 0").unwrap_err().message, "Modulo by zero.");
+        assert_eq!(eval("\"a\" % 2").unwrap_err().message, "Operands must be numbers.");
+    }
+}
@@ -0,0 +1,49 @@
+//! A tree-walking interpreter for Lox, the language from Crafting Interpreters.
+//!
+//! ```
+//! let value = rlox::run_source("var a = 1; a + 2;").unwrap();
+//! assert_eq!(value, rlox::Value::Number(3.0));
+//! ```
+//!
+//! Use a [`Lox`] session to keep globals between runs, or a [`Scanner`] on
+//! its own to get at the tokens.
+
+// Errors carry a whole token plus a stack trace. They're raised at most once
+// per run, so passing them by value costs nothing worth boxing them for.
+#![allow(clippy::result_large_err)]
+
+mod scanner;
+mod lox;
+mod token;
+mod error;
+mod expr;
+mod stmt;
+mod parser;
+mod interpreter;
+mod environment;
+mod value;
+mod callable;
+mod function;
+mod class;
+mod resolver;
+mod diagnostics;
+mod ast_printer;
+mod native;
+mod line_editor;
+mod repl;
+mod renderer;
+mod map;
+
+pub use crate::diagnostics::{Diagnostic, Severity};
+pub use crate::error::{Frame, LoxError, ParseError, ResolveError, RuntimeError, ScanError};
+pub use crate::lox::{Lox, Mode};
+pub use crate::renderer::ErrorFormat;
+pub use crate::scanner::Scanner;
+pub use crate::token::{Literal, Span, Token, TokenType};
+pub use crate::value::Value;
+
+/// Runs `source` in a fresh session, returning the value of its last
+/// statement if that's an expression statement, or `nil`.
+pub fn run_source(source: &str) -> Result<Value, LoxError> {
+    Lox::new().run_source(source)
+}
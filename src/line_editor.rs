@@ -0,0 +1,325 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+/// What the user did with a line at the prompt.
+#[derive(Debug, PartialEq)]
+pub(crate) enum ReadLine {
+    Line(String),
+    /// Ctrl-C: the line was abandoned.
+    Interrupted,
+    /// Ctrl-D on an empty line, or stdin closed.
+    Eof,
+}
+
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToStart,
+    KillToEnd,
+    Interrupt,
+    Eof,
+    Other,
+}
+
+/// A small readline-style editor for the REPL: cursor movement, history
+/// on the arrow keys and persistent history in `~/.rlox_history`. Only
+/// understands the handful of escape sequences common terminals send, and
+/// falls back to plain line reading when stdin isn't a terminal.
+pub(crate) struct LineEditor {
+    history: Vec<String>,
+    history_path: Option<PathBuf>,
+}
+
+impl LineEditor {
+    pub(crate) fn new() -> Self {
+        let history_path = env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlox_history"));
+        let history = history_path.as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|s| s.lines().map(str::to_string).collect())
+            .unwrap_or_default();
+        LineEditor { history, history_path }
+    }
+
+    pub(crate) fn read_line(&mut self, prompt: &str) -> io::Result<ReadLine> {
+        let stdin = io::stdin();
+        let mut stdout = io::stdout();
+
+        if !stdin.is_terminal() {
+            write!(stdout, "{}", prompt)?;
+            stdout.flush()?;
+
+            let mut line = String::new();
+            if stdin.read_line(&mut line)? == 0 {
+                return Ok(ReadLine::Eof);
+            }
+            return Ok(ReadLine::Line(line.trim_end_matches(['\n', '\r']).to_string()));
+        }
+
+        let _raw = RawMode::enable()?;
+        let result = self.edit(prompt, &mut stdin.lock(), &mut stdout);
+        write!(stdout, "\r\n")?;
+        result
+    }
+
+    /// Records `line` in the history and appends it to the history file.
+    pub(crate) fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+
+        if let Some(path) = &self.history_path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    /// Edits one line read key by key from `input`, redrawing it on `output`.
+    fn edit(&self, prompt: &str, input: &mut impl Read, output: &mut impl Write) -> io::Result<ReadLine> {
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        // Position in history while browsing with the arrow keys, and the
+        // line being edited before browsing started.
+        let mut history_index = self.history.len();
+        let mut draft: Vec<char> = vec![];
+
+        Self::redraw(output, prompt, &line, cursor)?;
+        loop {
+            match Self::read_key(input)? {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => return Ok(ReadLine::Line(line.into_iter().collect())),
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::Up if history_index > 0 => {
+                    if history_index == self.history.len() {
+                        draft = line;
+                    }
+                    history_index -= 1;
+                    line = self.history[history_index].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if history_index < self.history.len() => {
+                    history_index += 1;
+                    line = match self.history.get(history_index) {
+                        Some(entry) => entry.chars().collect(),
+                        None => std::mem::take(&mut draft),
+                    };
+                    cursor = line.len();
+                }
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::KillToEnd => line.truncate(cursor),
+                Key::Interrupt => {
+                    write!(output, "^C")?;
+                    return Ok(ReadLine::Interrupted);
+                }
+                Key::Eof if line.is_empty() => return Ok(ReadLine::Eof),
+                Key::Eof if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                _ => continue,
+            }
+            Self::redraw(output, prompt, &line, cursor)?;
+        }
+    }
+
+    fn redraw(output: &mut impl Write, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+        let text: String = line.iter().collect();
+        write!(output, "\r{}{}\x1b[K", prompt, text)?;
+        if cursor < line.len() {
+            write!(output, "\x1b[{}D", line.len() - cursor)?;
+        }
+        output.flush()
+    }
+
+    fn read_key(input: &mut impl Read) -> io::Result<Key> {
+        let Some(b) = Self::read_byte(input)? else { return Ok(Key::Eof) };
+
+        let key = match b {
+            b'\r' | b'\n' => Key::Enter,
+            0x7f | 0x08 => Key::Backspace,
+            0x01 => Key::Home,
+            0x05 => Key::End,
+            0x02 => Key::Left,
+            0x06 => Key::Right,
+            0x10 => Key::Up,
+            0x0e => Key::Down,
+            0x15 => Key::KillToStart,
+            0x0b => Key::KillToEnd,
+            0x03 => Key::Interrupt,
+            0x04 => Key::Eof,
+            0x1b => Self::read_escape(input)?,
+            b if b < 0x20 => Key::Other,
+            b => Self::read_char(input, b)?,
+        };
+        Ok(key)
+    }
+
+    /// Decodes the rest of an `ESC [` or `ESC O` sequence.
+    fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+        if !matches!(Self::read_byte(input)?, Some(b'[') | Some(b'O')) {
+            return Ok(Key::Other);
+        }
+
+        let key = match Self::read_byte(input)? {
+            Some(b'A') => Key::Up,
+            Some(b'B') => Key::Down,
+            Some(b'C') => Key::Right,
+            Some(b'D') => Key::Left,
+            Some(b'H') => Key::Home,
+            Some(b'F') => Key::End,
+            // `ESC [ n ~`, where n is one or more digits.
+            Some(d) if d.is_ascii_digit() => {
+                let mut n = vec![d];
+                loop {
+                    match Self::read_byte(input)? {
+                        Some(b'~') | None => break,
+                        Some(d) => n.push(d),
+                    }
+                }
+                match n.as_slice() {
+                    b"1" | b"7" => Key::Home,
+                    b"4" | b"8" => Key::End,
+                    b"3" => Key::Delete,
+                    _ => Key::Other,
+                }
+            }
+            _ => Key::Other,
+        };
+        Ok(key)
+    }
+
+    /// Reads the continuation bytes of a UTF-8 character starting with `first`.
+    fn read_char(input: &mut impl Read, first: u8) -> io::Result<Key> {
+        let len = match first {
+            b if b < 0x80 => 1,
+            b if b >> 5 == 0b110 => 2,
+            b if b >> 4 == 0b1110 => 3,
+            b if b >> 3 == 0b11110 => 4,
+            _ => return Ok(Key::Other),
+        };
+
+        let mut bytes = vec![first];
+        for _ in 1..len {
+            match Self::read_byte(input)? {
+                Some(b) => bytes.push(b),
+                None => return Ok(Key::Other),
+            }
+        }
+
+        Ok(std::str::from_utf8(&bytes).ok()
+            .and_then(|s| s.chars().next())
+            .map_or(Key::Other, Key::Char))
+    }
+
+    fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match input.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
+
+/// Puts the terminal in raw mode, so keys arrive one at a time without echo,
+/// until dropped. Uses `stty` rather than termios bindings to stay free of
+/// dependencies.
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let saved = Self::stty(&["-g"])?;
+        Self::stty(&["raw", "-echo"])?;
+        Ok(RawMode { saved: saved.trim().to_string() })
+    }
+
+    fn stty(args: &[&str]) -> io::Result<String> {
+        let output = Command::new("stty").args(args).stdin(Stdio::inherit()).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other("stty failed"));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Self::stty(&[&self.saved]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor(history: &[&str]) -> LineEditor {
+        LineEditor { history: history.iter().map(|h| h.to_string()).collect(), history_path: None }
+    }
+
+    fn edit(editor: &LineEditor, keys: &str) -> ReadLine {
+        editor.edit("> ", &mut keys.as_bytes(), &mut vec![]).unwrap()
+    }
+
+    #[test]
+    fn test_editing() {
+        let e = editor(&[]);
+        assert_eq!(edit(&e, "print 1;\r"), ReadLine::Line("print 1;".to_string()));
+        assert_eq!(edit(&e, "prnt\x1b[D\x1b[Di\r"), ReadLine::Line("print".to_string()));
+        assert_eq!(edit(&e, "abc\x7f\x7fx\r"), ReadLine::Line("ax".to_string()));
+        assert_eq!(edit(&e, "bc\x01a\x05d\r"), ReadLine::Line("abcd".to_string()));
+        assert_eq!(edit(&e, "abc\x1b[D\x1b[D\x1b[3~\r"), ReadLine::Line("ac".to_string()));
+        assert_eq!(edit(&e, "junk\x15ok\r"), ReadLine::Line("ok".to_string()));
+        assert_eq!(edit(&e, "π → λ\r"), ReadLine::Line("π → λ".to_string()));
+    }
+
+    #[test]
+    fn test_interrupt_and_eof() {
+        let e = editor(&[]);
+        assert_eq!(edit(&e, "half a line\x03"), ReadLine::Interrupted);
+        assert_eq!(edit(&e, "\x04"), ReadLine::Eof);
+        assert_eq!(edit(&e, ""), ReadLine::Eof);
+        assert_eq!(edit(&e, "ab\x02\x04\r"), ReadLine::Line("a".to_string()));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut e = editor(&["var a = 1;"]);
+        e.add_history("print a;");
+        e.add_history("print a;");
+        e.add_history("   ");
+        assert_eq!(e.history, vec!["var a = 1;", "print a;"]);
+
+        assert_eq!(edit(&e, "\x1b[A\r"), ReadLine::Line("print a;".to_string()));
+        assert_eq!(edit(&e, "\x1b[A\x1b[A\x1b[A\r"), ReadLine::Line("var a = 1;".to_string()));
+        assert_eq!(edit(&e, "draft\x1b[A\x1b[B\r"), ReadLine::Line("draft".to_string()));
+    }
+}
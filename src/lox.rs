@@ -1,51 +1,315 @@
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::stdin;
+use std::fs;
+use std::io::{self, IsTerminal};
 use std::process::exit;
+use std::thread;
+use crate::ast_printer::AstPrinter;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::error::LoxError;
+use crate::interpreter::Interpreter;
+use crate::parser::Parser;
+use crate::renderer::{ErrorFormat, Renderer};
+use crate::repl::Repl;
+use crate::resolver::Resolver;
 use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::Token;
+use crate::value::Value;
 
-pub(crate) struct Lox {
+/// The stack size of the thread programs run on: enough for `MAX_CALL_DEPTH`
+/// calls even in an unoptimized build.
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// How far `Lox::run` takes its input: all the way, or only far enough to dump
+/// the tokens or the syntax tree.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Mode {
+    #[default]
+    Run,
+    Tokens,
+    Ast,
+}
+
+/// An interpreter session. All error state lives on the instance rather than
+/// in globals, so independent sessions can run side by side, one per thread.
+pub struct Lox {
+    pub(crate) interpreter: Interpreter,
+    diagnostics: Diagnostics,
+    renderer: Renderer,
+    mode: Mode,
 }
 
-static mut HAD_ERROR: bool = false;
+impl Default for Lox {
+    fn default() -> Self {
+        Lox::new()
+    }
+}
 
 impl Lox {
-    pub(crate) fn new() -> Self {
-        Lox {}
+    pub fn new() -> Self {
+        Lox::new_with_mode(Mode::Run)
+    }
+
+    pub fn new_with_mode(mode: Mode) -> Self {
+        let renderer = Renderer::new(io::stderr().is_terminal());
+        Lox { interpreter: Interpreter::new(), diagnostics: Diagnostics::new(), renderer, mode }
+    }
+
+    /// Whether errors are printed with ANSI colors. By default they are when
+    /// stderr is a terminal.
+    pub fn set_color(&mut self, color: bool) {
+        self.renderer.set_color(color);
+    }
+
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.renderer.set_format(format);
+    }
+
+    /// With IEEE math, dividing by zero gives `Infinity`, `-Infinity` or `NaN`
+    /// rather than a "Division by zero." runtime error, which is the default.
+    pub fn set_ieee_math(&mut self, ieee_math: bool) {
+        self.interpreter.ieee_math = ieee_math;
+    }
+
+    /// In deterministic mode `random()` always gives the same sequence, and
+    /// `clock()` reads a virtual clock that starts at 0 and only advances by
+    /// what `sleep(ms)` is asked for, without actually waiting. Map iteration
+    /// follows insertion order in either mode.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.interpreter.define_time_natives(deterministic);
+    }
+
+    /// Makes a Rust function callable from Lox code as the global `name`. The
+    /// function gets exactly `arity` arguments; an `Err` it returns becomes a
+    /// runtime error at the call. It must be `Send + Sync` so the session can
+    /// still move to another thread.
+    pub fn define_native(&mut self, name: &str, arity: usize, function: impl Fn(&[Value]) -> Result<Value, String> + Send + Sync + 'static) {
+        self.interpreter.define_native(name, arity, Box::new(function));
+    }
+
+    /// Runs `source` in this session without printing any errors, returning
+    /// the value of its last statement if that's an expression statement.
+    /// Globals defined by earlier calls are still visible.
+    pub fn run_source(&mut self, source: &str) -> Result<Value, LoxError> {
+        let statements = Self::parse(source, false)?;
+        self.execute(&statements)
+    }
+
+    fn scan(source: &str) -> Result<Vec<Token>, LoxError> {
+        Scanner::new(source.to_string()).scan_tokens()
+            .map_err(|errors| LoxError::Compile(errors.into_iter().map(Diagnostic::from).collect()))
+    }
+
+    /// In the REPL, a lone expression with no semicolon is also accepted, and
+    /// becomes a `print` statement so its value is echoed.
+    fn parse(source: &str, repl: bool) -> Result<Vec<Stmt>, LoxError> {
+        let tokens = Self::scan(source)?;
+        let mut parser = Parser::new(tokens);
+        let statements = if repl { parser.parse_repl() } else { parser.parse() };
+        statements.map_err(|errors| LoxError::Compile(errors.into_iter().map(Diagnostic::from).collect()))
+    }
+
+    /// Like `parse`, but for dumping the tree: a statement that fails to parse
+    /// shows up as an `(error ...)` node, and the errors come back alongside.
+    fn parse_partial(source: &str, repl: bool) -> Result<(Vec<Stmt>, Vec<Diagnostic>), LoxError> {
+        let tokens = Self::scan(source)?;
+        if repl {
+            if let Ok(statements) = Parser::new(tokens.clone()).parse_repl() {
+                return Ok((statements, vec![]));
+            }
+        }
+
+        let (statements, errors) = Parser::new(tokens).parse_partial();
+        Ok((statements, errors.into_iter().map(Diagnostic::from).collect()))
+    }
+
+    /// Resolves and then interprets parsed statements.
+    fn execute(&mut self, statements: &[Stmt]) -> Result<Value, LoxError> {
+        let mut resolver = Resolver::new();
+        resolver.resolve(statements);
+        if !resolver.errors.is_empty() {
+            return Err(LoxError::Compile(resolver.errors.into_iter().map(Diagnostic::from).collect()));
+        }
+
+        // Each Lox call takes several Rust frames, more than the usual thread
+        // stack has room for at the maximum call depth.
+        let interpreter = &mut self.interpreter;
+        let result = thread::scope(|scope| {
+            thread::Builder::new()
+                .stack_size(INTERPRETER_STACK_SIZE)
+                .spawn_scoped(scope, || interpreter.interpret(statements))
+                .expect("failed to start the interpreter thread")
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        });
+        result.map_err(LoxError::Runtime)
+    }
+
+    /// Runs `input` as far as the mode says, printing any errors. `name` is
+    /// the file it came from, for error locations.
+    pub(crate) fn run(&mut self, name: &str, input: &str, repl: bool) {
+        self.run_with_mode(name, input, self.mode, repl);
     }
 
-    pub(crate) fn run(&self, input: String) {
-        let mut scanner = Scanner::new(input);
-        let tokens = scanner.scan_tokens();
-        
-        for t in tokens {
-            println!("{:?}", t);
+    pub(crate) fn run_with_mode(&mut self, name: &str, input: &str, mode: Mode, repl: bool) {
+        let result = match mode {
+            Mode::Run => Self::parse(input, repl).and_then(|statements| self.execute(&statements)).map(|_| ()),
+            Mode::Tokens => Self::scan(input).map(|tokens| {
+                println!("{}", Token::TABLE_HEADER);
+                for t in &tokens {
+                    println!("{}", t);
+                }
+            }),
+            Mode::Ast => Self::parse_partial(input, repl).and_then(|(statements, errors)| {
+                for s in &statements {
+                    println!("{}", AstPrinter.print(s));
+                }
+                if errors.is_empty() { Ok(()) } else { Err(LoxError::Compile(errors)) }
+            }),
+        };
+
+        if let Err(e) = result {
+            for d in e.into_diagnostics() {
+                self.report(name, input, d);
+            }
         }
     }
-    
-    pub(crate) fn run_file(&self, file_name: &String) {
-        let mut file = File::open(file_name).unwrap();
-        let mut s = String::new();
-        let _ = file.read_to_string(&mut s);
-        self.run(s);
-    }
-    
-    pub(crate) fn run_prompt(&mut self) {
-        loop {
-            if unsafe { HAD_ERROR } {
-                exit(65);
+
+    /// Records `diagnostic` and prints it, pointing into `source`.
+    fn report(&mut self, name: &str, source: &str, diagnostic: impl Into<Diagnostic>) {
+        let diagnostic = self.diagnostics.report(diagnostic);
+        eprintln!("{}", self.renderer.render(diagnostic, name, source));
+    }
+
+    /// Reads a script, or returns a message saying why it couldn't be read.
+    pub(crate) fn read_file(file_name: &str) -> Result<String, String> {
+        fs::read_to_string(file_name).map_err(|e| format!("Could not open file '{}': {}", file_name, e))
+    }
+
+    /// Exits with 74 if the file can't be read, 65 on a compile error and 70
+    /// on a runtime error.
+    pub fn run_file(&mut self, file_name: &str) {
+        let source = match Self::read_file(file_name) {
+            Ok(source) => source,
+            Err(m) => {
+                eprintln!("{}", m);
+                exit(74);
             }
-            let mut buffer = String::new();
-            unsafe { HAD_ERROR = false };
-            print!("> ");
-            let stdin = stdin();
-            let _ = stdin.read_line(&mut buffer);
-            self.run(buffer);
+        };
+        self.run(file_name, &source, false);
+
+        if self.diagnostics.had_error() {
+            exit(65);
+        }
+        if self.diagnostics.had_runtime_error() {
+            exit(70);
         }
     }
 
-    pub(crate) fn error(line: u32, m: &str) {
-        eprint!("[line {}] Error {}", line, m);
-        unsafe { HAD_ERROR = true };
+    pub fn run_prompt(&mut self) {
+        Repl::new(self).run();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn test_sessions_are_isolated() {
+        assert_send::<Lox>();
+
+        // Sessions are built here and moved to the worker threads, with
+        // functions and lists already in their globals.
+        let mut bad = Lox::new();
+        bad.run("test.lox", "fun f() { return [1]; } var xs = f();", false);
+        let mut good = Lox::new();
+        good.run("test.lox", "var xs = [2];", false);
+
+        let bad = std::thread::spawn(move || {
+            bad.run("test.lox", "@", false);
+            bad.diagnostics.had_error()
+        });
+        let good = std::thread::spawn(move || {
+            good.run("test.lox", "xs[0] + 2;", false);
+            good.diagnostics.had_error()
+        });
+
+        assert!(bad.join().unwrap());
+        assert!(!good.join().unwrap());
+    }
+
+    #[test]
+    fn test_run_source_returns_last_expression() {
+        let mut lox = Lox::new();
+        assert_eq!(lox.run_source("var a = 1;"), Ok(Value::Nil));
+        assert_eq!(lox.run_source("a + 2;"), Ok(Value::Number(3.0)));
+        assert_eq!(lox.run_source("a; print a;"), Ok(Value::Nil));
+        assert!(!lox.diagnostics.had_error());
+    }
+
+    #[test]
+    fn test_run_source_errors() {
+        let mut lox = Lox::new();
+
+        let err = lox.run_source("@ $").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error: Unexpected character.\n[line 1] Error: Unexpected character.");
+
+        let err = lox.run_source("return 1;").unwrap_err();
+        assert!(matches!(err, LoxError::Compile(ref d) if d.len() == 1));
+
+        let err = lox.run_source("-nil;").unwrap_err();
+        assert_eq!(err.to_string(), "Operand must be a number.\n[line 1]");
+        assert_eq!(err.into_diagnostics()[0].severity(), crate::diagnostics::Severity::RuntimeError);
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        let mut lox = Lox::new();
+        let err = lox.run_source("fun f() { f(); }\nf();").unwrap_err();
+        let LoxError::Runtime(ref e) = err else { panic!("expected a runtime error") };
+        assert_eq!(e.message, "Stack overflow.");
+        assert_eq!(e.trace.len(), crate::interpreter::MAX_CALL_DEPTH);
+        assert_eq!(err.to_string(), format!(
+            "Stack overflow.\n[line 1] in f()\n[previous line repeated {} more times]\n[line 2] in script",
+            crate::interpreter::MAX_CALL_DEPTH - 1,
+        ));
+
+        // The session is still usable afterwards, at full depth.
+        assert_eq!(lox.run_source("fun g(n) { if (n == 0) return 0; return g(n - 1) + 1; } g(900);"), Ok(Value::Number(900.0)));
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let source = "
+            var m = {\"b\": 1, \"a\": 2};
+            var start = clock();
+            sleep(1500);
+            [start, clock(), keys(m), random(), random()];
+        ";
+        let run = || {
+            let mut lox = Lox::new();
+            lox.set_deterministic(true);
+            lox.run_source(source).unwrap().to_string()
+        };
+
+        let first = run();
+        assert!(first.starts_with("[0, 1.5, [\"b\", \"a\"], "), "{}", first);
+        assert_eq!(first, run());
+    }
+
+    #[test]
+    fn test_define_native() {
+        let mut lox = Lox::new();
+        lox.define_native("double", 1, |args| match args[0] {
+            Value::Number(n) => Ok(Value::Number(n * 2.0)),
+            _ => Err("Expected a number.".to_string()),
+        });
+
+        assert_eq!(lox.run_source("double(21);"), Ok(Value::Number(42.0)));
+
+        let err = lox.run_source("\n\ndouble(\"x\");").unwrap_err();
+        assert_eq!(err.to_string(), "Expected a number.\n[line 3]");
     }
 }
@@ -1,19 +1,127 @@
-mod scanner;
-mod lox;
-mod token;
-mod Expr;
-
 use std::env;
-use crate::lox::Lox;
+use std::process::exit;
+use rlox::{ErrorFormat, Lox, Mode};
+
+const USAGE: &str = "Usage: rlox [--tokens | --ast] [--ieee-math] [--deterministic] [--no-color] [--error-format=human|json] [script]
+       rlox --version";
+
+#[derive(Debug, PartialEq, Default)]
+struct Options {
+    mode: Mode,
+    script: Option<String>,
+    ieee_math: bool,
+    deterministic: bool,
+    no_color: bool,
+    error_format: ErrorFormat,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Version,
+    Run(Options),
+}
+
+/// Parses the arguments after the program name, or returns `None` if they
+/// don't fit the usage.
+fn parse_args(args: &[String]) -> Option<Command> {
+    let mut options = Options::default();
+
+    for arg in args {
+        match arg.as_str() {
+            "--version" => return Some(Command::Version),
+            "--tokens" if options.mode == Mode::Run => options.mode = Mode::Tokens,
+            "--ast" if options.mode == Mode::Run => options.mode = Mode::Ast,
+            "--ieee-math" => options.ieee_math = true,
+            "--deterministic" => options.deterministic = true,
+            "--no-color" => options.no_color = true,
+            "--error-format=human" => options.error_format = ErrorFormat::Human,
+            "--error-format=json" => options.error_format = ErrorFormat::Json,
+            a if a.starts_with('-') => return None,
+            a if options.script.is_none() => options.script = Some(a.to_string()),
+            _ => return None,
+        }
+    }
+
+    Some(Command::Run(options))
+}
+
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let mut lox = Lox::new();
-
-    if args.len() > 1 {
-        println!("Usage: rlox [script]");
-    } else if args.len() == 1 {
-        lox.run_file(&args[0]);
-    } else {
-        lox.run_prompt();
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match parse_args(&args) {
+        Some(Command::Version) => println!("rlox {}", env!("CARGO_PKG_VERSION")),
+        Some(Command::Run(options)) => {
+            let mut lox = Lox::new_with_mode(options.mode);
+            lox.set_ieee_math(options.ieee_math);
+            lox.set_deterministic(options.deterministic);
+            if options.no_color {
+                lox.set_color(false);
+            }
+            lox.set_error_format(options.error_format);
+
+            match options.script {
+                Some(script) => lox.run_file(&script),
+                None => lox.run_prompt(),
+            }
+        }
+        None => {
+            eprintln!("{}", USAGE);
+            exit(64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn script(name: &str) -> Option<String> {
+        Some(name.to_string())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args(&[])), Some(Command::Run(Options::default())));
+        assert_eq!(
+            parse_args(&args(&["test.lox"])),
+            Some(Command::Run(Options { script: script("test.lox"), ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["--ast", "test.lox"])),
+            Some(Command::Run(Options { mode: Mode::Ast, script: script("test.lox"), ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["--tokens"])),
+            Some(Command::Run(Options { mode: Mode::Tokens, ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["test.lox", "--ieee-math"])),
+            Some(Command::Run(Options { script: script("test.lox"), ieee_math: true, ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["--deterministic", "test.lox"])),
+            Some(Command::Run(Options { script: script("test.lox"), deterministic: true, ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["--no-color"])),
+            Some(Command::Run(Options { no_color: true, ..Options::default() })),
+        );
+        assert_eq!(
+            parse_args(&args(&["--error-format=json", "test.lox"])),
+            Some(Command::Run(Options { script: script("test.lox"), error_format: ErrorFormat::Json, ..Options::default() })),
+        );
+        assert_eq!(parse_args(&args(&["test.lox", "--version"])), Some(Command::Version));
+    }
+
+    #[test]
+    fn test_usage_errors() {
+        assert_eq!(parse_args(&args(&["a.lox", "b.lox"])), None);
+        assert_eq!(parse_args(&args(&["--tokens", "--ast"])), None);
+        assert_eq!(parse_args(&args(&["--bogus"])), None);
+        assert_eq!(parse_args(&args(&["--error-format=xml"])), None);
     }
 }
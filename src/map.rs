@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::value::{format_number, Value};
+
+/// A value usable as a map key. Only strings, numbers, booleans and `nil` can
+/// be keys: they compare by value, unlike lists, maps and instances, which
+/// compare by identity and can change after being inserted.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub(crate) enum MapKey {
+    Str(Arc<str>),
+    /// The bits of the number, with `-0` stored as `0` so the two are the
+    /// same key, as they are `==`.
+    Number(u64),
+    Bool(bool),
+    Nil,
+}
+
+impl MapKey {
+    pub(crate) fn new(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Str(s) => Ok(MapKey::Str(s.clone())),
+            Value::Number(n) if n.is_nan() => Err("NaN can't be a map key.".to_string()),
+            Value::Number(n) => Ok(MapKey::Number(if *n == 0.0 { 0.0f64.to_bits() } else { n.to_bits() })),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            Value::Nil => Ok(MapKey::Nil),
+            _ => Err("Map keys must be strings, numbers, booleans or nil.".to_string()),
+        }
+    }
+
+    pub(crate) fn to_value(&self) -> Value {
+        match self {
+            MapKey::Str(s) => Value::Str(s.clone()),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Nil => Value::Nil,
+        }
+    }
+
+    /// How the key appears in error messages: strings quoted, as in a literal.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            MapKey::Str(s) => format!("{:?}", s),
+            MapKey::Number(bits) => format_number(f64::from_bits(*bits)),
+            MapKey::Bool(b) => b.to_string(),
+            MapKey::Nil => "nil".to_string(),
+        }
+    }
+}
+
+/// A Lox map. Entries keep the order they were first inserted in, so `keys`,
+/// `values` and printing are predictable.
+#[derive(Default)]
+pub struct LoxMap {
+    entries: Vec<(MapKey, Value)>,
+    index: HashMap<MapKey, usize>,
+}
+
+impl LoxMap {
+    pub(crate) fn new() -> Self {
+        LoxMap::default()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub(crate) fn get(&self, key: &MapKey) -> Option<&Value> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    /// Replaces the value of an existing key in place, keeping its position.
+    pub(crate) fn insert(&mut self, key: MapKey, value: Value) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub(crate) fn remove(&mut self, key: &MapKey) -> Option<Value> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for later in self.index.values_mut().filter(|later| **later > i) {
+            *later -= 1;
+        }
+        Some(value)
+    }
+
+    pub(crate) fn entries(&self) -> impl Iterator<Item = (&MapKey, &Value)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: Value) -> MapKey {
+        MapKey::new(&value).unwrap()
+    }
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(key(Value::Number(0.0)), key(Value::Number(-0.0)));
+        assert_ne!(key(Value::Number(1.0)), key(Value::Str("1".into())));
+        assert_eq!(key(Value::Number(1.5)).to_value(), Value::Number(1.5));
+        assert!(MapKey::new(&Value::Number(f64::NAN)).is_err());
+        assert!(MapKey::new(&Value::List(Default::default())).is_err());
+    }
+
+    #[test]
+    fn test_insertion_order() {
+        let mut map = LoxMap::new();
+        for (k, v) in [("b", 1.0), ("a", 2.0), ("c", 3.0), ("b", 4.0)] {
+            map.insert(key(Value::Str(k.into())), Value::Number(v));
+        }
+        assert_eq!(map.remove(&key(Value::Str("a".into()))), Some(Value::Number(2.0)));
+        assert_eq!(map.remove(&key(Value::Str("a".into()))), None);
+
+        let entries: Vec<String> = map.entries().map(|(k, v)| format!("{}: {}", k.describe(), v)).collect();
+        assert_eq!(entries, vec!["\"b\": 4", "\"c\": 3"]);
+        assert_eq!(map.get(&key(Value::Str("c".into()))), Some(&Value::Number(3.0)));
+        assert_eq!(map.len(), 2);
+    }
+}
@@ -0,0 +1,275 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::callable::LoxCallable;
+use crate::error::RuntimeError;
+use crate::interpreter::Interpreter;
+use crate::map::{LoxMap, MapKey};
+use crate::token::Token;
+use crate::value::Value;
+
+/// The Rust side of a native function. An `Err` message is raised in Lox as a
+/// runtime error at the call.
+pub(crate) type NativeFn = dyn Fn(&[Value]) -> Result<Value, String> + Send + Sync;
+
+/// A function implemented in Rust and exposed to Lox code as a global.
+pub(crate) struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub(crate) fn new(name: &str, arity: usize, function: Box<NativeFn>) -> Self {
+        NativeFunction { name: name.to_string(), arity, function }
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &mut Interpreter, paren: &Token, arguments: Vec<Value>) -> Result<Value, RuntimeError> {
+        (self.function)(&arguments).map_err(|m| RuntimeError::new(paren, &m))
+    }
+
+    fn to_string(&self) -> String {
+        format!("<native fn {}>", self.name)
+    }
+}
+
+//Strings
+// Lengths and indices count chars, not bytes, so strings are never split
+// partway through a multibyte character.
+
+/// `len(x)`: the number of characters in a string, elements in a list or
+/// entries in a map.
+pub(crate) fn len(args: &[Value]) -> Result<Value, String> {
+    match &args[0] {
+        Value::Str(s) => Ok(Value::Number(s.chars().count() as f64)),
+        Value::List(list) => Ok(Value::Number(list.read().unwrap().len() as f64)),
+        Value::Map(map) => Ok(Value::Number(map.read().unwrap().len() as f64)),
+        _ => Err("Expected a string, a list or a map.".to_string()),
+    }
+}
+
+/// `substr(s, start, length)`: the `length` characters of `s` from index
+/// `start`. The range must lie within `s`.
+pub(crate) fn substr(args: &[Value]) -> Result<Value, String> {
+    let s = string_arg(&args[0])?;
+    let start = index_arg(&args[1])?;
+    let length = index_arg(&args[2])?;
+
+    let count = s.chars().count();
+    if start > count || length > count - start {
+        return Err(format!("Substring {}..{} out of bounds for string of length {}.", start, start + length, count));
+    }
+    Ok(Value::Str(s.chars().skip(start).take(length).collect::<String>().into()))
+}
+
+/// `upper(s)`: `s` with every letter in upper case.
+pub(crate) fn upper(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(string_arg(&args[0])?.to_uppercase().into()))
+}
+
+/// `lower(s)`: `s` with every letter in lower case.
+pub(crate) fn lower(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Str(string_arg(&args[0])?.to_lowercase().into()))
+}
+
+/// `contains(s, part)`: whether `part` occurs anywhere in `s`. For a map,
+/// `contains(map, key)` is whether it has an entry for `key`.
+pub(crate) fn contains(args: &[Value]) -> Result<Value, String> {
+    if let Value::Map(map) = &args[0] {
+        return Ok(Value::Bool(map.read().unwrap().get(&MapKey::new(&args[1])?).is_some()));
+    }
+
+    let s = string_arg(&args[0])?;
+    let part = string_arg(&args[1])?;
+    Ok(Value::Bool(s.contains(part)))
+}
+
+/// `split(s, separator)`: a list of the pieces of `s` between occurrences of
+/// `separator`, or of its characters if `separator` is empty.
+pub(crate) fn split(args: &[Value]) -> Result<Value, String> {
+    let s = string_arg(&args[0])?;
+    let separator = string_arg(&args[1])?;
+
+    let pieces: Vec<Value> = if separator.is_empty() {
+        s.chars().map(|c| Value::Str(c.to_string().into())).collect()
+    } else {
+        s.split(separator).map(|p| Value::Str(p.into())).collect()
+    };
+    Ok(Value::List(Arc::new(RwLock::new(pieces))))
+}
+
+//Lists
+/// `push(list, value)`: appends `value` to `list`.
+pub(crate) fn push(args: &[Value]) -> Result<Value, String> {
+    list_arg(&args[0])?.write().unwrap().push(args[1].clone());
+    Ok(Value::Nil)
+}
+
+/// `pop(list)`: removes and returns the last element of `list`.
+pub(crate) fn pop(args: &[Value]) -> Result<Value, String> {
+    list_arg(&args[0])?.write().unwrap().pop().ok_or_else(|| "Can't pop from an empty list.".to_string())
+}
+
+//Maps
+/// `keys(map)`: a list of the keys, in the order they were first inserted.
+pub(crate) fn keys(args: &[Value]) -> Result<Value, String> {
+    let keys = map_arg(&args[0])?.read().unwrap().entries().map(|(k, _)| k.to_value()).collect();
+    Ok(Value::List(Arc::new(RwLock::new(keys))))
+}
+
+/// `values(map)`: a list of the values, in the same order as `keys`.
+pub(crate) fn values(args: &[Value]) -> Result<Value, String> {
+    let values = map_arg(&args[0])?.read().unwrap().entries().map(|(_, v)| v.clone()).collect();
+    Ok(Value::List(Arc::new(RwLock::new(values))))
+}
+
+/// `remove(map, key)`: removes the entry for `key` and returns its value.
+pub(crate) fn remove(args: &[Value]) -> Result<Value, String> {
+    let key = MapKey::new(&args[1])?;
+    map_arg(&args[0])?.write().unwrap().remove(&key).ok_or_else(|| format!("Undefined key {}.", key.describe()))
+}
+
+//Math
+// Numbers are f64s, so results are exact only where f64 arithmetic is:
+// integers up to 2^53 survive `floor`, `ceil` and `abs` unchanged, while
+// `sqrt` and `pow` round to the nearest f64.
+
+/// `floor(x)`: the largest integer no greater than `x`.
+pub(crate) fn floor(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.floor()))
+}
+
+/// `ceil(x)`: the smallest integer no less than `x`.
+pub(crate) fn ceil(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.ceil()))
+}
+
+/// `abs(x)`: `x` without its sign.
+pub(crate) fn abs(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.abs()))
+}
+
+/// `sqrt(x)`. A negative `x` is an error rather than `NaN`.
+pub(crate) fn sqrt(args: &[Value]) -> Result<Value, String> {
+    match number_arg(&args[0])? {
+        x if x < 0.0 => Err("Can't take the square root of a negative number.".to_string()),
+        x => Ok(Value::Number(x.sqrt())),
+    }
+}
+
+/// `pow(base, exponent)`. Results too large for a number are `Infinity`.
+pub(crate) fn pow(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.powf(number_arg(&args[1])?)))
+}
+
+/// `min(a, b)`: the smaller of `a` and `b`.
+pub(crate) fn min(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.min(number_arg(&args[1])?)))
+}
+
+/// `max(a, b)`: the larger of `a` and `b`.
+pub(crate) fn max(args: &[Value]) -> Result<Value, String> {
+    Ok(Value::Number(number_arg(&args[0])?.max(number_arg(&args[1])?)))
+}
+
+/// `random()`: a number in `[0, 1)`, from an xorshift generator started at
+/// `seed`. Not suitable for anything secret.
+pub(crate) fn new_random(seed: u64) -> Box<NativeFn> {
+    let state = AtomicU64::new(seed | 1);
+
+    Box::new(move |_| {
+        let mut x = state.load(Ordering::Relaxed);
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.store(x, Ordering::Relaxed);
+        // The top 53 bits, scaled so every result is exactly representable.
+        Ok(Value::Number((x >> 11) as f64 / (1u64 << 53) as f64))
+    })
+}
+
+//Time
+/// A seed that differs from run to run: the current time in nanoseconds.
+pub(crate) fn time_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// `clock()`: seconds since the Unix epoch, with a fractional part.
+pub(crate) fn clock(_: &[Value]) -> Result<Value, String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?;
+    Ok(Value::Number(now.as_secs_f64()))
+}
+
+/// `sleep(ms)`: blocks for `ms` milliseconds, then returns `nil`.
+pub(crate) fn sleep(args: &[Value]) -> Result<Value, String> {
+    let duration = Duration::try_from_secs_f64(milliseconds_arg(&args[0])? / 1000.0)
+        .map_err(|_| "Sleep duration is too long.".to_string())?;
+    thread::sleep(duration);
+    Ok(Value::Nil)
+}
+
+/// `clock()` and `sleep(ms)` for deterministic mode. They share a virtual
+/// clock that starts at 0 and only moves when `sleep` is called, which
+/// returns at once.
+pub(crate) fn new_virtual_clock() -> (Box<NativeFn>, Box<NativeFn>) {
+    let now = Arc::new(Mutex::new(0.0));
+
+    let clock_now = Arc::clone(&now);
+    let clock = Box::new(move |_: &[Value]| Ok(Value::Number(*clock_now.lock().unwrap())));
+    let sleep = Box::new(move |args: &[Value]| {
+        *now.lock().unwrap() += milliseconds_arg(&args[0])? / 1000.0;
+        Ok(Value::Nil)
+    });
+    (clock, sleep)
+}
+
+//Helpers
+fn milliseconds_arg(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(ms) if *ms >= 0.0 && ms.is_finite() => Ok(*ms),
+        _ => Err("Expected a non-negative number of milliseconds.".to_string()),
+    }
+}
+
+fn number_arg(value: &Value) -> Result<f64, String> {
+    match value {
+        Value::Number(n) => Ok(*n),
+        _ => Err("Expected a number.".to_string()),
+    }
+}
+
+fn string_arg(value: &Value) -> Result<&str, String> {
+    match value {
+        Value::Str(s) => Ok(s),
+        _ => Err("Expected a string.".to_string()),
+    }
+}
+
+fn list_arg(value: &Value) -> Result<&Arc<RwLock<Vec<Value>>>, String> {
+    match value {
+        Value::List(list) => Ok(list),
+        _ => Err("Expected a list.".to_string()),
+    }
+}
+
+fn map_arg(value: &Value) -> Result<&Arc<RwLock<LoxMap>>, String> {
+    match value {
+        Value::Map(map) => Ok(map),
+        _ => Err("Expected a map.".to_string()),
+    }
+}
+
+fn index_arg(value: &Value) -> Result<usize, String> {
+    match value {
+        Value::Number(n) if *n >= 0.0 && n.fract() == 0.0 && n.is_finite() => Ok(*n as usize),
+        _ => Err("Expected a non-negative integer.".to_string()),
+    }
+}
@@ -0,0 +1,1475 @@
+use std::sync::{Arc, OnceLock};
+use crate::error::ParseError;
+use crate::expr::Expr;
+
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::{Literal, Token, TokenType};
+use crate::token::TokenType::*;
+
+pub(crate) struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+    /// Every error so far. Parsing carries on after each one, resuming at the
+    /// next statement boundary if need be.
+    errors: Vec<ParseError>,
+}
+
+impl Parser {
+    pub(crate) fn new(tokens: Vec<Token>) -> Self {
+        Parser { tokens, current: 0, errors: vec![] }
+    }
+
+    pub(crate) fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let (statements, errors) = self.parse_partial();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(statements)
+    }
+
+    /// Like `parse`, but the tree is returned even when there are errors, with
+    /// each statement that failed to parse replaced by a `Stmt::Error`.
+    pub(crate) fn parse_partial(&mut self) -> (Vec<Stmt>, Vec<ParseError>) {
+        let mut statements = vec![];
+        while !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+
+        (statements, std::mem::take(&mut self.errors))
+    }
+
+    /// Like `parse`, but input that is a single expression with no trailing
+    /// semicolon is accepted too, as a `print` statement, so the REPL can echo
+    /// the value of `1 + 2`.
+    pub(crate) fn parse_repl(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        if let Ok(expr) = self.expression() {
+            if self.is_at_end() && self.errors.is_empty() { return Ok(vec![Stmt::Print(expr)]); }
+        }
+
+        self.current = 0;
+        self.errors.clear();
+        self.parse()
+    }
+
+    //Statements
+    /// Usually one statement, but `var a, b;` declares each name with its own.
+    /// After an error, records it and skips ahead to where the next statement
+    /// probably starts, returning the skipped tokens as a `Stmt::Error`.
+    fn declaration(&mut self) -> Vec<Stmt> {
+        let start = self.current;
+        match self.try_declaration() {
+            Ok(stmts) => stmts,
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                vec![Stmt::Error(self.tokens[start..self.current].to_vec())]
+            }
+        }
+    }
+
+    fn try_declaration(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        if self.match_types(&[CLASS]) {
+            return Ok(vec![self.class_declaration()?]);
+        }
+        if self.match_types(&[TRAIT]) {
+            return Ok(vec![self.trait_declaration()?]);
+        }
+        // `fun (` starts an anonymous function in an expression statement;
+        // anything else after `fun` is a declaration and must be a name.
+        if self.check(FUN) && !self.check_next(LPAREN) {
+            self.advance();
+            return Ok(vec![self.function("function")?]);
+        }
+        if self.match_types(&[VAR]) {
+            return self.var_declaration();
+        }
+
+        Ok(vec![self.statement()?])
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(IDENT, "Expect class name.")?.clone();
+
+        let mut superclass = None;
+        if self.match_types(&[LT]) {
+            let name = self.consume(IDENT, "Expect superclass name.")?.clone();
+            superclass = Some(Expr::variable(name));
+        }
+
+        let mut traits = vec![];
+        if self.match_types(&[WITH]) {
+            loop {
+                let name = self.consume(IDENT, "Expect trait name.")?.clone();
+                traits.push(Expr::variable(name));
+                if !self.match_types(&[COMMA]) { break; }
+            }
+        }
+
+        self.consume(LBRACE, "Expect '{' before class body.")?;
+
+        let mut fields = vec![];
+        let mut methods = vec![];
+        let mut class_methods = vec![];
+        while !self.check(RBRACE) && !self.is_at_end() {
+            if self.match_types(&[VAR]) {
+                loop {
+                    fields.push(self.consume(IDENT, "Expect field name.")?.clone());
+                    if !self.match_types(&[COMMA]) { break; }
+                }
+                self.consume(SEMICOLON, "Expect ';' after field declaration.")?;
+            } else if self.match_types(&[CLASS]) {
+                class_methods.push(self.method_declaration()?);
+            } else {
+                methods.push(self.method_declaration()?);
+            }
+        }
+
+        self.consume(RBRACE, "Expect '}' after class body.")?;
+        Ok(Stmt::Class { name, superclass, traits, fields, methods, class_methods })
+    }
+
+    fn trait_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume(IDENT, "Expect trait name.")?.clone();
+        self.consume(LBRACE, "Expect '{' before trait body.")?;
+
+        let mut methods = vec![];
+        while !self.check(RBRACE) && !self.is_at_end() {
+            methods.push(self.method_declaration()?);
+        }
+
+        self.consume(RBRACE, "Expect '}' after trait body.")?;
+        Ok(Stmt::Trait { name, methods })
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, ParseError> {
+        Ok(Stmt::Function(self.function_declaration(kind)?))
+    }
+
+    fn function_declaration(&mut self, kind: &str) -> Result<Arc<FunctionDecl>, ParseError> {
+        let name = self.consume(IDENT, &format!("Expect {} name.", kind))?.clone();
+        self.consume(LPAREN, &format!("Expect '(' after {} name.", kind))?;
+        self.function_body(name, kind)
+    }
+
+    /// A method, or a getter if the name is followed straight by the body.
+    fn method_declaration(&mut self) -> Result<Arc<FunctionDecl>, ParseError> {
+        let name = self.consume(IDENT, "Expect method name.")?.clone();
+        if self.match_types(&[LBRACE]) {
+            let body = self.block()?;
+            return Ok(Arc::new(FunctionDecl { name, params: vec![], body, is_getter: true }));
+        }
+
+        self.consume(LPAREN, "Expect '(' after method name.")?;
+        self.function_body(name, "method")
+    }
+
+    /// Parses the parameters and body, from just after the `(`.
+    fn function_body(&mut self, name: Token, kind: &str) -> Result<Arc<FunctionDecl>, ParseError> {
+        let mut params = vec![];
+        if !self.check(RPAREN) {
+            loop {
+                if params.len() >= 255 {
+                    return Err(ParseError::new(self.peek(), "Can't have more than 255 parameters."));
+                }
+                params.push(self.consume(IDENT, "Expect parameter name.")?.clone());
+
+                if !self.match_types(&[COMMA]) { break; }
+            }
+        }
+        self.consume(RPAREN, "Expect ')' after parameters.")?;
+
+        self.consume(LBRACE, &format!("Expect '{{' before {} body.", kind))?;
+        let body = self.block()?;
+
+        Ok(Arc::new(FunctionDecl { name, params, body, is_getter: false }))
+    }
+
+    /// Initializers are parsed below the comma operator, so in
+    /// `var a = 1, b = 2;` the comma separates declarations.
+    fn var_declaration(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut declarations = vec![];
+        loop {
+            if self.match_types(&[LBRACKET]) {
+                declarations.push(self.destructuring_declaration()?);
+            } else {
+                let name = self.consume(IDENT, "Expect variable name.")?.clone();
+
+                let mut initializer = None;
+                if self.match_types(&[EQ]) {
+                    initializer = Some(self.assignment()?);
+                }
+                declarations.push(Stmt::Var { name, initializer });
+            }
+
+            if !self.match_types(&[COMMA]) { break; }
+        }
+
+        self.consume(SEMICOLON, "Expect ';' after variable declaration.")?;
+        Ok(declarations)
+    }
+
+    /// `var [a, b] = list`, from just after the `[`.
+    fn destructuring_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let mut names = vec![];
+        loop {
+            names.push(self.consume(IDENT, "Expect variable name.")?.clone());
+            if !self.match_types(&[COMMA]) { break; }
+        }
+        let bracket = self.consume(RBRACKET, "Expect ']' after variable names.")?.clone();
+
+        self.consume(EQ, "Expect '=' after destructuring pattern.")?;
+        let initializer = self.assignment()?;
+        Ok(Stmt::Destructure { names, bracket, initializer })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_types(&[DO]) {
+            return self.do_while_statement();
+        }
+        if self.match_types(&[FOR]) {
+            return self.for_statement();
+        }
+        if self.match_types(&[IF]) {
+            return self.if_statement();
+        }
+        if self.match_types(&[PRINT]) {
+            return self.print_statement();
+        }
+        if self.match_types(&[RETURN]) {
+            return self.return_statement();
+        }
+        if self.match_types(&[WHILE]) {
+            return self.while_statement();
+        }
+        if self.match_types(&[LBRACE]) {
+            return Ok(Stmt::Block(self.block()?));
+        }
+
+        self.expression_statement()
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let body = Box::new(self.statement()?);
+        self.consume(WHILE, "Expect 'while' after do loop body.")?;
+        self.consume(LPAREN, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(RPAREN, "Expect ')' after condition.")?;
+        self.consume(SEMICOLON, "Expect ';' after do loop condition.")?;
+
+        Ok(Stmt::DoWhile { body, condition })
+    }
+
+    /// There is no `for` node: the loop is desugared into its initializer
+    /// followed by a `while` whose body runs the increment after each pass.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(LPAREN, "Expect '(' after 'for'.")?;
+
+        let mut initializer = if self.match_types(&[SEMICOLON]) {
+            vec![]
+        } else if self.match_types(&[VAR]) {
+            self.var_declaration()?
+        } else {
+            vec![self.expression_statement()?]
+        };
+
+        let mut condition = None;
+        if !self.check(SEMICOLON) {
+            condition = Some(self.expression()?);
+        }
+        self.consume(SEMICOLON, "Expect ';' after loop condition.")?;
+
+        let mut increment = None;
+        if !self.check(RPAREN) {
+            increment = Some(self.expression()?);
+        }
+        self.consume(RPAREN, "Expect ')' after for clauses.")?;
+
+        let mut body = self.statement()?;
+
+        if let Some(increment) = increment {
+            body = Stmt::Block(vec![body, Stmt::Expression(increment)]);
+        }
+
+        let condition = condition.unwrap_or(Expr::Literal(Literal::Bool(true)));
+        body = Stmt::While { condition, body: Box::new(body) };
+
+        if !initializer.is_empty() {
+            initializer.push(body);
+            body = Stmt::Block(initializer);
+        }
+
+        Ok(body)
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(LPAREN, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(RPAREN, "Expect ')' after if condition.")?;
+
+        let then_branch = Box::new(self.statement()?);
+        let mut else_branch = None;
+        if self.match_types(&[ELSE]) {
+            else_branch = Some(Box::new(self.statement()?));
+        }
+
+        Ok(Stmt::If { condition, then_branch, else_branch })
+    }
+
+    fn print_statement(&mut self) -> Result<Stmt, ParseError> {
+        let value = self.expression()?;
+        self.consume(SEMICOLON, "Expect ';' after value.")?;
+        Ok(Stmt::Print(value))
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+
+        let mut value = None;
+        if !self.check(SEMICOLON) {
+            value = Some(self.expression()?);
+        }
+
+        self.consume(SEMICOLON, "Expect ';' after return value.")?;
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(LPAREN, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(RPAREN, "Expect ')' after condition.")?;
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While { condition, body })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = vec![];
+
+        while !self.check(RBRACE) && !self.is_at_end() {
+            statements.extend(self.declaration());
+        }
+
+        self.consume(RBRACE, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, ParseError> {
+        let expr = self.expression()?;
+        self.consume(SEMICOLON, "Expect ';' after expression.")?;
+        Ok(Stmt::Expression(expr))
+    }
+
+    //Expressions
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.comma()
+    }
+
+    /// `a, b, c` evaluates each operand in turn and yields the last. Where
+    /// commas separate things, as in argument lists, parsing starts at
+    /// `assignment` instead, so a comma expression there must be parenthesized.
+    fn comma(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.assignment()?;
+
+        while self.match_types(&[COMMA]) {
+            let operator = self.previous().clone();
+            let right = self.assignment()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.conditional()?;
+
+        if self.match_types(&[EQ, PLUSEQ, MINUSEQ, STAREQ, SLASHEQ]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            let operator = match equals.token_type {
+                PLUSEQ => Some(PLUS),
+                MINUSEQ => Some(MINUS),
+                STAREQ => Some(STAR),
+                SLASHEQ => Some(SLASH),
+                _ => None,
+            };
+
+            match (expr, operator) {
+                (target @ (Expr::Variable { .. } | Expr::Get { .. } | Expr::Index { .. }), Some(token_type)) => {
+                    let operator = Token { token_type, ..equals };
+                    return Ok(Expr::Compound { target: Box::new(target), operator, value: Box::new(value) });
+                }
+                (Expr::Variable { name, .. }, None) => return Ok(Expr::assign(name, value)),
+                (Expr::Get { object, name }, None) => return Ok(Expr::Set { object, name, value: Box::new(value) }),
+                (Expr::Index { object, bracket, index }, None) => {
+                    return Ok(Expr::SetIndex { object, bracket, index, value: Box::new(value) });
+                }
+                _ => (),
+            }
+
+            return Err(ParseError::new(&equals, "Invalid assignment target."));
+        }
+
+        Ok(expr)
+    }
+
+    /// Right-associative, so `a ? b : c ? d : e` is `a ? b : (c ? d : e)`. As
+    /// in C, any expression may appear between `?` and `:`.
+    fn conditional(&mut self) -> Result<Expr, ParseError> {
+        let condition = self.or()?;
+
+        if self.match_types(&[QUESTION]) {
+            let then_branch = self.expression()?;
+            self.consume(COLON, "Expect ':' after then branch of conditional expression.")?;
+            let else_branch = self.conditional()?;
+            return Ok(Expr::Conditional {
+                condition: Box::new(condition),
+                then_branch: Box::new(then_branch),
+                else_branch: Box::new(else_branch),
+            });
+        }
+
+        Ok(condition)
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.and()?;
+
+        while self.match_types(&[OR]) {
+            let operator = self.previous().clone();
+            let right = self.and()?;
+            expr = Expr::Logical { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.equality()?;
+
+        while self.match_types(&[AND]) {
+            let operator = self.previous().clone();
+            let right = self.equality()?;
+            expr = Expr::Logical { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.comparison()?;
+
+        while self.match_types(&[BANGEQ, EQEQ]) {
+            let operator = self.previous().clone();
+            let right = self.comparison()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_or()?;
+
+        while self.match_types(&[GT, GTEQ, LT, LTEQ]) {
+            let operator = self.previous().clone();
+            let right = self.bit_or()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    /// The bitwise operators bind tighter than comparisons, as in Python, so
+    /// `x & 1 == 0` tests the low bit of `x`.
+    fn bit_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.match_types(&[PIPE]) {
+            let operator = self.previous().clone();
+            let right = self.bit_xor()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.bit_and()?;
+
+        while self.match_types(&[CARET]) {
+            let operator = self.previous().clone();
+            let right = self.bit_and()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.shift()?;
+
+        while self.match_types(&[AMP]) {
+            let operator = self.previous().clone();
+            let right = self.shift()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.term()?;
+
+        while self.match_types(&[GTGT, LTLT]) {
+            let operator = self.previous().clone();
+            let right = self.term()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.factor()?;
+
+        while self.match_types(&[MINUS, PLUS]) {
+            let operator = self.previous().clone();
+            let right = self.factor()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.unary()?;
+
+        while self.match_types(&[SLASH, STAR, PERCENT]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            expr = Expr::Binary { left: Box::new(expr), operator, right: Box::new(right) };
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_types(&[BANG, MINUS, TILDE]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+            return Ok(Expr::Unary { operator, right: Box::new(right) });
+        }
+
+        if self.match_types(&[PLUSPLUS, MINUSMINUS]) {
+            let operator = self.previous().clone();
+            let target = self.unary()?;
+            return Self::update(target, operator, true);
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.call()?;
+
+        if self.match_types(&[PLUSPLUS, MINUSMINUS]) {
+            let operator = self.previous().clone();
+            return Self::update(expr, operator, false);
+        }
+
+        Ok(expr)
+    }
+
+    /// Like assignment, `++` and `--` only apply to variables, properties and
+    /// indexed elements.
+    fn update(target: Expr, operator: Token, prefix: bool) -> Result<Expr, ParseError> {
+        match target {
+            Expr::Variable { .. } | Expr::Get { .. } | Expr::Index { .. } => {
+                Ok(Expr::Update { target: Box::new(target), operator, prefix })
+            }
+            _ => Err(ParseError::new(&operator, &format!("Invalid '{}' target.", operator.lexeme))),
+        }
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_types(&[LPAREN]) {
+                expr = self.finish_call(expr)?;
+            } else if self.match_types(&[DOT]) {
+                let name = self.consume(IDENT, "Expect property name after '.'.")?.clone();
+                expr = Expr::Get { object: Box::new(expr), name };
+            } else if self.match_types(&[LBRACKET]) {
+                expr = self.finish_index(expr)?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    /// `object[index]`, or a slice, `object[start..end]`, where either bound
+    /// can be left out.
+    fn finish_index(&mut self, object: Expr) -> Result<Expr, ParseError> {
+        let object = Box::new(object);
+        let start = if self.check(DOTDOT) { None } else { Some(Box::new(self.expression()?)) };
+
+        match start {
+            Some(index) if !self.match_types(&[DOTDOT]) => {
+                let bracket = self.consume(RBRACKET, "Expect ']' after index.")?.clone();
+                Ok(Expr::Index { object, bracket, index })
+            }
+            start => {
+                self.match_types(&[DOTDOT]);
+                let end = if self.check(RBRACKET) { None } else { Some(Box::new(self.expression()?)) };
+                let bracket = self.consume(RBRACKET, "Expect ']' after slice.")?.clone();
+                Ok(Expr::Slice { object, bracket, start, end })
+            }
+        }
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, ParseError> {
+        let mut arguments = vec![];
+        if !self.check(RPAREN) {
+            loop {
+                if arguments.len() >= 255 {
+                    return Err(ParseError::new(self.peek(), "Can't have more than 255 arguments."));
+                }
+                arguments.push(self.assignment()?);
+
+                if !self.match_types(&[COMMA]) { break; }
+            }
+        }
+
+        let paren = self.consume(RPAREN, "Expect ')' after arguments.")?.clone();
+
+        Ok(Expr::Call { callee: Box::new(callee), paren, arguments })
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_types(&[FALSE]) { return Ok(Expr::Literal(Literal::Bool(false))); }
+        if self.match_types(&[TRUE]) { return Ok(Expr::Literal(Literal::Bool(true))); }
+        if self.match_types(&[NIL]) { return Ok(Expr::Literal(Literal::Nil)); }
+
+        if self.match_types(&[NUM, STRING]) {
+            return Ok(Expr::Literal(self.previous().literal.clone()));
+        }
+
+        if self.match_types(&[INTERPOLATION]) {
+            return self.interpolation();
+        }
+
+        if self.match_types(&[FUN]) {
+            let keyword = self.previous().clone();
+            self.consume(LPAREN, "Expect '(' after 'fun'.")?;
+            return Ok(Expr::Function(self.function_body(keyword, "function")?));
+        }
+
+        if self.match_types(&[SUPER]) {
+            let keyword = self.previous().clone();
+            self.consume(DOT, "Expect '.' after 'super'.")?;
+            let method = self.consume(IDENT, "Expect superclass method name.")?.clone();
+            return Ok(Expr::Super { keyword, method, depth: OnceLock::new() });
+        }
+
+        if self.match_types(&[THIS]) {
+            return Ok(Expr::This { keyword: self.previous().clone(), depth: OnceLock::new() });
+        }
+
+        if self.match_types(&[IDENT]) {
+            return Ok(Expr::variable(self.previous().clone()));
+        }
+
+        if self.match_types(&[LPAREN]) {
+            let expr = self.expression()?;
+            self.consume(RPAREN, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping(Box::new(expr)));
+        }
+
+        if self.match_types(&[LBRACKET]) {
+            let mut elements = vec![];
+            if !self.check(RBRACKET) {
+                loop {
+                    elements.push(self.assignment()?);
+                    if !self.match_types(&[COMMA]) { break; }
+                }
+            }
+            self.consume(RBRACKET, "Expect ']' after list elements.")?;
+            return Ok(Expr::List(elements));
+        }
+
+        // A `{` starting a statement was already taken as a block, so one here
+        // is a map.
+        if self.match_types(&[LBRACE]) {
+            let mut entries = vec![];
+            if !self.check(RBRACE) {
+                loop {
+                    let key = self.assignment()?;
+                    self.consume(COLON, "Expect ':' after map key.")?;
+                    entries.push((key, self.assignment()?));
+                    if !self.match_types(&[COMMA]) { break; }
+                }
+            }
+            let brace = self.consume(RBRACE, "Expect '}' after map entries.")?.clone();
+            return Ok(Expr::Map { brace, entries });
+        }
+
+        if self.match_types(&[BANGEQ, EQEQ, GT, GTEQ, LT, LTEQ, PLUS, SLASH, STAR, PERCENT, AMP, PIPE, CARET, LTLT, GTGT]) {
+            return self.missing_left_operand();
+        }
+
+        Err(ParseError::new(self.peek(), "Expect expression."))
+    }
+
+    /// Error production for a binary operator at the start of an operand, as
+    /// in `== 2`. The right operand is parsed at the operator's own precedence
+    /// and stands in for the whole expression, so parsing carries on with a
+    /// single error. `-` isn't covered since it's also a prefix operator.
+    fn missing_left_operand(&mut self) -> Result<Expr, ParseError> {
+        let operator = self.previous().clone();
+        self.errors.push(ParseError::new(&operator, "Binary operator missing left-hand operand."));
+
+        match operator.token_type {
+            BANGEQ | EQEQ => self.comparison(),
+            GT | GTEQ | LT | LTEQ => self.bit_or(),
+            PIPE => self.bit_xor(),
+            CARET => self.bit_and(),
+            AMP => self.shift(),
+            LTLT | GTGT => self.term(),
+            PLUS => self.factor(),
+            _ => self.unary(),
+        }
+    }
+
+    /// The scanner splits `"a ${x} b ${y} c"` into `INTERPOLATION` pieces for
+    /// `"a ${` and `} b ${`, with the expressions in between, and a final
+    /// `STRING` for `} c"`.
+    fn interpolation(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = vec![Expr::Literal(self.previous().literal.clone())];
+
+        loop {
+            parts.push(self.expression()?);
+
+            if self.match_types(&[STRING]) {
+                parts.push(Expr::Literal(self.previous().literal.clone()));
+                return Ok(Expr::Interpolation(parts));
+            }
+
+            self.consume(INTERPOLATION, "Expect '}' after interpolated expression.")?;
+            parts.push(Expr::Literal(self.previous().literal.clone()));
+        }
+    }
+
+    //Helpers
+    /// Discards tokens until just after a semicolon or just before a keyword
+    /// that starts a statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type == SEMICOLON { return; }
+
+            match self.peek().token_type {
+                CLASS | TRAIT | FUN | VAR | DO | FOR | IF | WHILE | PRINT | RETURN => return,
+                _ => { self.advance(); }
+            }
+        }
+    }
+
+    fn match_types(&mut self, types: &[TokenType]) -> bool {
+        for t in types {
+            if self.check(*t) {
+                self.advance();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn consume(&mut self, t: TokenType, message: &str) -> Result<&Token, ParseError> {
+        if self.check(t) { return Ok(self.advance()); }
+
+        Err(ParseError::new(self.peek(), message))
+    }
+
+    fn check(&self, t: TokenType) -> bool {
+        if self.is_at_end() { return false; }
+
+        self.peek().token_type == t
+    }
+
+    fn check_next(&self, t: TokenType) -> bool {
+        if self.is_at_end() { return false; }
+
+        self.tokens[self.current + 1].token_type == t
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() { self.current += 1; }
+
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        self.peek().token_type == EOF
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    /// Fails with the first error.
+    fn parse(input: &str) -> Result<Vec<Stmt>, ParseError> {
+        parse_all(input).map_err(|errors| errors[0].clone())
+    }
+
+    fn parse_all(input: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut scanner = Scanner::new(input.to_string());
+        let tokens = scanner.scan_tokens().unwrap();
+        Parser::new(tokens).parse()
+    }
+
+    fn messages(input: &str) -> Vec<String> {
+        parse_all(input).unwrap_err().into_iter().map(|e| e.to_string()).collect()
+    }
+
+    fn parse_expr(input: &str) -> Expr {
+        match parse(&format!("{};", input)).unwrap().remove(0) {
+            Stmt::Expression(expr) => expr,
+            s => panic!("expected expression statement, got {:?}", s),
+        }
+    }
+
+    fn num(n: f64) -> Expr {
+        Expr::Literal(Literal::Num(n))
+    }
+
+    fn binary(left: Expr, t: TokenType, lexeme: &str, right: Expr) -> Expr {
+        Expr::Binary { left: Box::new(left), operator: Token::new(t, lexeme, 1), right: Box::new(right) }
+    }
+
+    #[test]
+    fn test_precedence() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+        let expr = parse_expr("1 + 2 * 3 == 7");
+
+        let exp = binary(
+            binary(num(1.0), PLUS, "+", binary(num(2.0), STAR, "*", num(3.0))),
+            EQEQ, "==",
+            num(7.0),
+        );
+        assert_eq!(expr, exp);
+
+        assert_eq!(parse_expr("a | b ^ c & d << 1 < e"), binary(
+            binary(
+                var("a"),
+                PIPE, "|",
+                binary(var("b"), CARET, "^", binary(var("c"), AMP, "&", binary(var("d"), LTLT, "<<", num(1.0)))),
+            ),
+            LT, "<",
+            var("e"),
+        ));
+        assert_eq!(parse_expr("~a >> 1 + 1"), binary(
+            Expr::Unary { operator: Token::new(TILDE, "~", 1), right: Box::new(var("a")) },
+            GTGT, ">>",
+            binary(num(1.0), PLUS, "+", num(1.0)),
+        ));
+        assert_eq!(parse_expr("1 - 6 % 4 * 2"), binary(
+            num(1.0),
+            MINUS, "-",
+            binary(binary(num(6.0), PERCENT, "%", num(4.0)), STAR, "*", num(2.0)),
+        ));
+    }
+
+    #[test]
+    fn test_left_associativity() {
+        let expr = parse_expr("1 - 2 - 3");
+
+        let exp = binary(binary(num(1.0), MINUS, "-", num(2.0)), MINUS, "-", num(3.0));
+        assert_eq!(expr, exp);
+    }
+
+    #[test]
+    fn test_unary_and_grouping() {
+        let expr = parse_expr("-(1 + 2) < !true");
+
+        let exp = binary(
+            Expr::Unary {
+                operator: Token::new(MINUS, "-", 1),
+                right: Box::new(Expr::Grouping(Box::new(binary(num(1.0), PLUS, "+", num(2.0))))),
+            },
+            LT, "<",
+            Expr::Unary { operator: Token::new(BANG, "!", 1), right: Box::new(Expr::Literal(Literal::Bool(true))) },
+        );
+        assert_eq!(expr, exp);
+    }
+
+    #[test]
+    fn test_logical_precedence() {
+        let expr = parse_expr("a or b and c == d");
+
+        let var = |n: &str| Box::new(Expr::variable(Token::new(IDENT, n, 1)));
+        let exp = Expr::Logical {
+            left: var("a"),
+            operator: Token::new(OR, "or", 1),
+            right: Box::new(Expr::Logical {
+                left: var("b"),
+                operator: Token::new(AND, "and", 1),
+                right: Box::new(binary(*var("c"), EQEQ, "==", *var("d"))),
+            }),
+        };
+        assert_eq!(expr, exp);
+    }
+
+    #[test]
+    fn test_variables_and_assignment() {
+        let expr = parse_expr("a = b = c + 1");
+
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+        let exp = Expr::assign(
+            Token::new(IDENT, "a", 1),
+            Expr::assign(Token::new(IDENT, "b", 1), binary(var("c"), PLUS, "+", num(1.0))),
+        );
+        assert_eq!(expr, exp);
+
+        let err = parse("a + b = 1;").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at '=': Invalid assignment target.");
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+
+        let compound = |target: Expr, t: TokenType, lexeme: &str, value: Expr| Expr::Compound {
+            target: Box::new(target),
+            operator: Token::new(t, lexeme, 1),
+            value: Box::new(value),
+        };
+
+        assert_eq!(
+            parse_expr("a -= b *= 2"),
+            compound(var("a"), MINUS, "-=", compound(var("b"), STAR, "*=", num(2.0))),
+        );
+
+        let get = Expr::Get { object: Box::new(var("o")), name: Token::new(IDENT, "f", 1) };
+        assert_eq!(parse_expr("o.f /= 2"), compound(get, SLASH, "/=", num(2.0)));
+
+        let index = Expr::Index {
+            object: Box::new(var("xs")),
+            bracket: Token::new(RBRACKET, "]", 1),
+            index: Box::new(num(0.0)),
+        };
+        assert_eq!(parse_expr("xs[0] += 1"), compound(index, PLUS, "+=", num(1.0)));
+
+        let err = parse("a + b += 1;").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at '+=': Invalid assignment target.");
+    }
+
+    #[test]
+    fn test_increment_and_decrement() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+        let update = |target: Expr, t: TokenType, lexeme: &str, prefix: bool| Expr::Update {
+            target: Box::new(target),
+            operator: Token::new(t, lexeme, 1),
+            prefix,
+        };
+
+        assert_eq!(parse_expr("i++"), update(var("i"), PLUSPLUS, "++", false));
+        assert_eq!(parse_expr("--i"), update(var("i"), MINUSMINUS, "--", true));
+        // Postfix binds tighter than prefix operators.
+        assert_eq!(parse_expr("-o.n--"), Expr::Unary {
+            operator: Token::new(MINUS, "-", 1),
+            right: Box::new(update(
+                Expr::Get { object: Box::new(var("o")), name: Token::new(IDENT, "n", 1) },
+                MINUSMINUS, "--", false,
+            )),
+        });
+
+        assert_eq!(parse("1++;").unwrap_err().to_string(), "[line 1] Error at '++': Invalid '++' target.");
+        assert_eq!(parse("++i++;").unwrap_err().message, "Invalid '++' target.");
+        assert_eq!(parse("--f();").unwrap_err().message, "Invalid '--' target.");
+    }
+
+    #[test]
+    fn test_conditional() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+        let conditional = |c: Expr, t: Expr, e: Expr| Expr::Conditional {
+            condition: Box::new(c),
+            then_branch: Box::new(t),
+            else_branch: Box::new(e),
+        };
+
+        assert_eq!(
+            parse_expr("a == 1 ? b : c == 2"),
+            conditional(binary(var("a"), EQEQ, "==", num(1.0)), var("b"), binary(var("c"), EQEQ, "==", num(2.0))),
+        );
+        assert_eq!(
+            parse_expr("a ? b : c ? d : e"),
+            conditional(var("a"), var("b"), conditional(var("c"), var("d"), var("e"))),
+        );
+        assert_eq!(
+            parse_expr("a ? b = 1 : c"),
+            conditional(var("a"), Expr::assign(Token::new(IDENT, "b", 1), num(1.0)), var("c")),
+        );
+        assert_eq!(
+            parse_expr("x = a ? b : c"),
+            Expr::assign(Token::new(IDENT, "x", 1), conditional(var("a"), var("b"), var("c"))),
+        );
+
+        let err = parse("a ? b : c = 1;").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at '=': Invalid assignment target.");
+
+        let err = parse("a ? b;").unwrap_err();
+        assert_eq!(err.message, "Expect ':' after then branch of conditional expression.");
+    }
+
+    #[test]
+    fn test_comma() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+
+        assert_eq!(
+            parse_expr("a, b = 1, c"),
+            binary(binary(var("a"), COMMA, ",", Expr::assign(Token::new(IDENT, "b", 1), num(1.0))), COMMA, ",", var("c")),
+        );
+
+        let call = |input: &str| match parse_expr(input) {
+            Expr::Call { arguments, .. } => arguments,
+            e => panic!("expected call, got {:?}", e),
+        };
+        assert_eq!(call("f(1, 2)"), vec![num(1.0), num(2.0)]);
+        assert_eq!(call("f((1, 2))"), vec![Expr::Grouping(Box::new(binary(num(1.0), COMMA, ",", num(2.0))))]);
+    }
+
+    #[test]
+    fn test_statements() {
+        let stmts = parse("print \"hi\"; nil;").unwrap();
+
+        assert_eq!(stmts, vec![
+            Stmt::Print(Expr::Literal(Literal::Str("hi".into()))),
+            Stmt::Expression(Expr::Literal(Literal::Nil)),
+        ]);
+    }
+
+    #[test]
+    fn test_var_declarations() {
+        let stmts = parse("var a; var b = 1;").unwrap();
+
+        assert_eq!(stmts, vec![
+            Stmt::Var { name: Token::new(IDENT, "a", 1), initializer: None },
+            Stmt::Var { name: Token::new(IDENT, "b", 1), initializer: Some(num(1.0)) },
+        ]);
+
+        let err = parse("var 1 = 2;").unwrap_err();
+        assert_eq!(err.message, "Expect variable name.");
+    }
+
+    #[test]
+    fn test_multiple_var_declarations() {
+        let ident = |n: &str| Token::new(IDENT, n, 1);
+        let stmts = parse("var a = 1, b, [c, d] = e;").unwrap();
+
+        assert_eq!(stmts, vec![
+            Stmt::Var { name: ident("a"), initializer: Some(num(1.0)) },
+            Stmt::Var { name: ident("b"), initializer: None },
+            Stmt::Destructure {
+                names: vec![ident("c"), ident("d")],
+                bracket: Token::new(RBRACKET, "]", 1),
+                initializer: Expr::variable(ident("e")),
+            },
+        ]);
+
+        // In a `for` initializer, the declarations share the loop's scope.
+        let Stmt::Block(stmts) = parse("for (var i = 0, j = 1; i < j;) {}").unwrap().remove(0) else {
+            panic!("expected block");
+        };
+        assert_eq!(stmts.len(), 3);
+
+        assert_eq!(parse("var a = 1, 2;").unwrap_err().message, "Expect variable name.");
+        assert_eq!(parse("var [] = xs;").unwrap_err().message, "Expect variable name.");
+        assert_eq!(parse("var [a, b];").unwrap_err().message, "Expect '=' after destructuring pattern.");
+        assert_eq!(parse("var [a, b = xs;").unwrap_err().message, "Expect ']' after variable names.");
+    }
+
+    #[test]
+    fn test_blocks() {
+        let stmts = parse("{ var a = 1; { print a; } }").unwrap();
+
+        assert_eq!(stmts, vec![
+            Stmt::Block(vec![
+                Stmt::Var { name: Token::new(IDENT, "a", 1), initializer: Some(num(1.0)) },
+                Stmt::Block(vec![Stmt::Print(Expr::variable(Token::new(IDENT, "a", 1)))]),
+            ]),
+        ]);
+
+        let err = parse("{ print 1;").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at end: Expect '}' after block.");
+    }
+
+    #[test]
+    fn test_dangling_else_binds_to_nearest_if() {
+        let stmts = parse("if (true) if (false) print 1; else print 2;").unwrap();
+
+        let t = |b| Expr::Literal(Literal::Bool(b));
+        assert_eq!(stmts, vec![
+            Stmt::If {
+                condition: t(true),
+                then_branch: Box::new(Stmt::If {
+                    condition: t(false),
+                    then_branch: Box::new(Stmt::Print(num(1.0))),
+                    else_branch: Some(Box::new(Stmt::Print(num(2.0)))),
+                }),
+                else_branch: None,
+            },
+        ]);
+
+        let err = parse("if true print 1;").unwrap_err();
+        assert_eq!(err.message, "Expect '(' after 'if'.");
+    }
+
+    #[test]
+    fn test_for_desugars_to_while() {
+        let stmts = parse("for (var i = 0; i < 3; i = i + 1) print i;").unwrap();
+
+        let i = || Expr::variable(Token::new(IDENT, "i", 1));
+        assert_eq!(stmts, vec![
+            Stmt::Block(vec![
+                Stmt::Var { name: Token::new(IDENT, "i", 1), initializer: Some(num(0.0)) },
+                Stmt::While {
+                    condition: binary(i(), LT, "<", num(3.0)),
+                    body: Box::new(Stmt::Block(vec![
+                        Stmt::Print(i()),
+                        Stmt::Expression(Expr::assign(Token::new(IDENT, "i", 1), binary(i(), PLUS, "+", num(1.0)))),
+                    ])),
+                },
+            ]),
+        ]);
+    }
+
+    #[test]
+    fn test_do_while() {
+        let stmts = parse("do print 1; while (false);").unwrap();
+        assert_eq!(stmts, vec![
+            Stmt::DoWhile { body: Box::new(Stmt::Print(num(1.0))), condition: Expr::Literal(Literal::Bool(false)) },
+        ]);
+
+        assert_eq!(parse("do {} (true);").unwrap_err().message, "Expect 'while' after do loop body.");
+        assert_eq!(parse("do {} while (true)").unwrap_err().message, "Expect ';' after do loop condition.");
+    }
+
+    #[test]
+    fn test_for_with_empty_clauses() {
+        let stmts = parse("for (;;) print 1;").unwrap();
+
+        assert_eq!(stmts, vec![
+            Stmt::While {
+                condition: Expr::Literal(Literal::Bool(true)),
+                body: Box::new(Stmt::Print(num(1.0))),
+            },
+        ]);
+
+        let stmts = parse("for (; false;) {}").unwrap();
+        assert_eq!(stmts, vec![
+            Stmt::While { condition: Expr::Literal(Literal::Bool(false)), body: Box::new(Stmt::Block(vec![])) },
+        ]);
+    }
+
+    #[test]
+    fn test_functions() {
+        let stmts = parse("fun add(a, b) { return a + b; } add(1, 2)(3);").unwrap();
+
+        let ident = |n: &str| Token::new(IDENT, n, 1);
+        let var = |n: &str| Expr::variable(ident(n));
+        assert_eq!(stmts, vec![
+            Stmt::Function(Arc::new(FunctionDecl {
+                name: ident("add"),
+                params: vec![ident("a"), ident("b")],
+                body: vec![Stmt::Return {
+                    keyword: Token::new(RETURN, "return", 1),
+                    value: Some(binary(var("a"), PLUS, "+", var("b"))),
+                }],
+                is_getter: false,
+            })),
+            Stmt::Expression(Expr::Call {
+                callee: Box::new(Expr::Call {
+                    callee: Box::new(var("add")),
+                    paren: Token::new(RPAREN, ")", 1),
+                    arguments: vec![num(1.0), num(2.0)],
+                }),
+                paren: Token::new(RPAREN, ")", 1),
+                arguments: vec![num(3.0)],
+            }),
+        ]);
+
+        let err = parse("fun f(a b) {}").unwrap_err();
+        assert_eq!(err.message, "Expect ')' after parameters.");
+
+        let err = parse("f(1;").unwrap_err();
+        assert_eq!(err.message, "Expect ')' after arguments.");
+    }
+
+    #[test]
+    fn test_anonymous_functions() {
+        let stmts = parse("fun (a) { print a; }; thrice(fun () {});").unwrap();
+
+        let Stmt::Expression(Expr::Function(decl)) = &stmts[0] else { panic!("expected function expression") };
+        assert!(decl.is_anonymous());
+        assert_eq!(decl.params, vec![Token::new(IDENT, "a", 1)]);
+        assert_eq!(decl.body, vec![Stmt::Print(Expr::variable(Token::new(IDENT, "a", 1)))]);
+
+        let Stmt::Expression(Expr::Call { arguments, .. }) = &stmts[1] else { panic!("expected call") };
+        assert!(matches!(&arguments[0], Expr::Function(decl) if decl.params.is_empty()));
+
+        let Stmt::Function(decl) = &parse("fun named() {}").unwrap()[0] else { panic!("expected declaration") };
+        assert!(!decl.is_anonymous());
+
+        let err = parse("var f = fun {};").unwrap_err();
+        assert_eq!(err.message, "Expect '(' after 'fun'.");
+    }
+
+    #[test]
+    fn test_lists_and_indexing() {
+        let var = |n: &str| Expr::variable(Token::new(IDENT, n, 1));
+        let index = |object: Expr, i: Expr| Expr::Index {
+            object: Box::new(object),
+            bracket: Token::new(RBRACKET, "]", 1),
+            index: Box::new(i),
+        };
+
+        assert_eq!(parse_expr("[]"), Expr::List(vec![]));
+        assert_eq!(parse_expr("[1, [2]]"), Expr::List(vec![num(1.0), Expr::List(vec![num(2.0)])]));
+        assert_eq!(parse_expr("xs[0][i + 1]"), index(index(var("xs"), num(0.0)), binary(var("i"), PLUS, "+", num(1.0))));
+        assert_eq!(parse_expr("f()[0]"), index(
+            Expr::Call { callee: Box::new(var("f")), paren: Token::new(RPAREN, ")", 1), arguments: vec![] },
+            num(0.0),
+        ));
+        assert_eq!(parse_expr("xs[0] = 1"), Expr::SetIndex {
+            object: Box::new(var("xs")),
+            bracket: Token::new(RBRACKET, "]", 1),
+            index: Box::new(num(0.0)),
+            value: Box::new(num(1.0)),
+        });
+
+        assert_eq!(parse("[1, 2;").unwrap_err().message, "Expect ']' after list elements.");
+        assert_eq!(parse("xs[0;").unwrap_err().message, "Expect ']' after index.");
+    }
+
+    #[test]
+    fn test_slices() {
+        let slice = |start: Option<Expr>, end: Option<Expr>| Expr::Slice {
+            object: Box::new(Expr::variable(Token::new(IDENT, "s", 1))),
+            bracket: Token::new(RBRACKET, "]", 1),
+            start: start.map(Box::new),
+            end: end.map(Box::new),
+        };
+
+        assert_eq!(parse_expr("s[1..3]"), slice(Some(num(1.0)), Some(num(3.0))));
+        assert_eq!(parse_expr("s[1..]"), slice(Some(num(1.0)), None));
+        assert_eq!(parse_expr("s[..-1]"), slice(None, Some(Expr::Unary { operator: Token::new(MINUS, "-", 1), right: Box::new(num(1.0)) })));
+        assert_eq!(parse_expr("s[..]"), slice(None, None));
+
+        assert_eq!(parse("s[1..2] = 3;").unwrap_err().message, "Invalid assignment target.");
+        assert_eq!(parse("s[1..2;").unwrap_err().message, "Expect ']' after slice.");
+    }
+
+    #[test]
+    fn test_maps() {
+        let str = |s: &str| Expr::Literal(Literal::Str(s.into()));
+        let map = |entries| Expr::Map { brace: Token::new(RBRACE, "}", 1), entries };
+
+        let Stmt::Var { initializer: Some(init), .. } = parse("var m = {\"a\": 1, 2: {}};").unwrap().remove(0) else {
+            panic!("expected var");
+        };
+        assert_eq!(init, map(vec![(str("a"), num(1.0)), (num(2.0), map(vec![]))]));
+
+        // At the start of a statement, braces are still a block.
+        assert_eq!(parse("{}").unwrap(), vec![Stmt::Block(vec![])]);
+        assert_eq!(parse_expr("({})"), Expr::Grouping(Box::new(map(vec![]))));
+
+        assert_eq!(parse("var m = {1 2};").unwrap_err().message, "Expect ':' after map key.");
+        assert_eq!(parse("var m = {1: 2;").unwrap_err().message, "Expect '}' after map entries.");
+    }
+
+    #[test]
+    fn test_classes_and_properties() {
+        let stmts = parse("class A { init(x) { this.x = x; } class make() {} get() { return this.x; } } a.b.c = d.e;").unwrap();
+
+        let ident = |n: &str| Token::new(IDENT, n, 1);
+        let Stmt::Class { name, superclass, methods, class_methods, .. } = &stmts[0] else { panic!("expected class") };
+        assert_eq!(name, &ident("A"));
+        assert_eq!(superclass, &None);
+        assert_eq!(methods.len(), 2);
+        assert_eq!(class_methods.len(), 1);
+        assert_eq!(class_methods[0].name, ident("make"));
+        assert!(!methods[1].is_getter);
+        assert_eq!(methods[0].name, ident("init"));
+        assert_eq!(methods[0].body, vec![Stmt::Expression(Expr::Set {
+            object: Box::new(Expr::This { keyword: Token::new(THIS, "this", 1), depth: OnceLock::new() }),
+            name: ident("x"),
+            value: Box::new(Expr::variable(ident("x"))),
+        })]);
+
+        let get = |object: Expr, n: &str| Expr::Get { object: Box::new(object), name: ident(n) };
+        assert_eq!(stmts[1], Stmt::Expression(Expr::Set {
+            object: Box::new(get(Expr::variable(ident("a")), "b")),
+            name: ident("c"),
+            value: Box::new(get(Expr::variable(ident("d")), "e")),
+        }));
+
+        let err = parse("a.1;").unwrap_err();
+        assert_eq!(err.message, "Expect property name after '.'.");
+    }
+
+    #[test]
+    fn test_field_declarations() {
+        let stmts = parse("class Point { var x, y; var z; }").unwrap();
+        let Stmt::Class { fields, .. } = &stmts[0] else { panic!("expected class") };
+        let names: Vec<&str> = fields.iter().map(|f| f.lexeme.as_str()).collect();
+        assert_eq!(names, vec!["x", "y", "z"]);
+
+        assert_eq!(parse("class P { var; }").unwrap_err().message, "Expect field name.");
+        assert_eq!(parse("class P { var x }").unwrap_err().message, "Expect ';' after field declaration.");
+    }
+
+    #[test]
+    fn test_traits() {
+        let stmts = parse("trait T { m() {} g { return 1; } } class C < B with T, U {}").unwrap();
+
+        let ident = |n: &str| Token::new(IDENT, n, 1);
+        let Stmt::Trait { name, methods } = &stmts[0] else { panic!("expected trait") };
+        assert_eq!(name, &ident("T"));
+        assert_eq!(methods.len(), 2);
+        assert!(methods[1].is_getter);
+
+        let Stmt::Class { superclass, traits, .. } = &stmts[1] else { panic!("expected class") };
+        assert_eq!(superclass, &Some(Expr::variable(ident("B"))));
+        assert_eq!(traits, &vec![Expr::variable(ident("T")), Expr::variable(ident("U"))]);
+
+        assert_eq!(parse("class C with {}").unwrap_err().message, "Expect trait name.");
+        assert_eq!(parse("trait T m() {}").unwrap_err().message, "Expect '{' before trait body.");
+    }
+
+    #[test]
+    fn test_getters() {
+        let stmts = parse("class Circle { area { return 3; } class unit { return 1; } }").unwrap();
+
+        let Stmt::Class { methods, class_methods, .. } = &stmts[0] else { panic!("expected class") };
+        assert!(methods[0].is_getter && methods[0].params.is_empty());
+        assert_eq!(methods[0].name, Token::new(IDENT, "area", 1));
+        assert!(class_methods[0].is_getter);
+
+        assert_eq!(parse("class A { m; }").unwrap_err().message, "Expect '(' after method name.");
+    }
+
+    #[test]
+    fn test_inheritance() {
+        let stmts = parse("class B < A { m() { return super.m(); } }").unwrap();
+
+        let ident = |n: &str| Token::new(IDENT, n, 1);
+        let Stmt::Class { superclass, methods, .. } = &stmts[0] else { panic!("expected class") };
+        assert_eq!(superclass, &Some(Expr::variable(ident("A"))));
+        assert_eq!(methods[0].body, vec![Stmt::Return {
+            keyword: Token::new(RETURN, "return", 1),
+            value: Some(Expr::Call {
+                callee: Box::new(Expr::Super {
+                    keyword: Token::new(SUPER, "super", 1),
+                    method: ident("m"),
+                    depth: OnceLock::new(),
+                }),
+                paren: Token::new(RPAREN, ")", 1),
+                arguments: vec![],
+            }),
+        }]);
+
+        let err = parse("super;").unwrap_err();
+        assert_eq!(err.message, "Expect '.' after 'super'.");
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let expr = parse_expr(r#""a ${1 + 2} b ${x}""#);
+
+        let str = |s: &str| Expr::Literal(Literal::Str(s.into()));
+        let exp = Expr::Interpolation(vec![
+            str("a "),
+            binary(num(1.0), PLUS, "+", num(2.0)),
+            str(" b "),
+            Expr::variable(Token::new(IDENT, "x", 1)),
+            str(""),
+        ]);
+        assert_eq!(expr, exp);
+
+        let err = parse(r#"print "${1 2}";"#).unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at '2': Expect '}' after interpolated expression.");
+    }
+
+    #[test]
+    fn test_repl_lone_expressions_are_printed() {
+        let parse_repl = |input: &str| Parser::new(Scanner::new(input.to_string()).scan_tokens().unwrap()).parse_repl();
+
+        assert_eq!(parse_repl("1 + 2"), Ok(vec![Stmt::Print(binary(num(1.0), PLUS, "+", num(2.0)))]));
+        assert_eq!(parse_repl("1 + 2;"), Ok(vec![Stmt::Expression(binary(num(1.0), PLUS, "+", num(2.0)))]));
+        assert_eq!(parse_repl("print 1;"), Ok(vec![Stmt::Print(num(1.0))]));
+        assert_eq!(
+            parse_repl("1 + 2 3").unwrap_err()[0].to_string(),
+            "[line 1] Error at '3': Expect ';' after expression.",
+        );
+        assert_eq!(parse_repl("== 1").unwrap_err().len(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = parse("print (1 + 2;").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at ';': Expect ')' after expression.");
+
+        let err = parse("1 +").unwrap_err();
+        assert_eq!(err.to_string(), "[line 1] Error at end: Expect expression.");
+    }
+
+    #[test]
+    fn test_synchronize_reports_independent_errors() {
+        assert_eq!(messages("var 1 = 2;\nprint (;\nvar ok = 3;\nprint ok +;"), vec![
+            "[line 1] Error at '1': Expect variable name.",
+            "[line 2] Error at ';': Expect expression.",
+            "[line 4] Error at ';': Expect expression.",
+        ]);
+
+        // Recovery stops before a keyword that starts a statement, and inside
+        // blocks parsing resumes with the block's next statement.
+        assert_eq!(messages("var = 1 if (a) print a;").len(), 1);
+        assert_eq!(messages("{ var; print 1; } fun 1() {} class A {}"), vec![
+            "[line 1] Error at ';': Expect variable name.",
+            "[line 1] Error at '1': Expect function name.",
+        ]);
+    }
+
+    #[test]
+    fn test_missing_left_operand() {
+        assert_eq!(messages("+ 1;"), vec!["[line 1] Error at '+': Binary operator missing left-hand operand."]);
+        assert_eq!(messages("print == 2;"), vec!["[line 1] Error at '==': Binary operator missing left-hand operand."]);
+
+        // The right operand is consumed at the operator's precedence, so the
+        // rest of the expression parses without further errors.
+        assert_eq!(messages("* 2 + 3 == 5;").len(), 1);
+        assert_eq!(messages("(> 1) ? a : b;").len(), 1);
+
+        let errors = messages("1 + (== 2)");
+        assert_eq!(errors, vec![
+            "[line 1] Error at '==': Binary operator missing left-hand operand.",
+            "[line 1] Error at end: Expect ';' after expression.",
+        ]);
+    }
+}
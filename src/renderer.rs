@@ -0,0 +1,275 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::token::{Span, Token};
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+/// How errors are printed: for people, or as one JSON object per line for
+/// editors and CI tools.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum ErrorFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+/// Renders diagnostics for the terminal: the message, then the file, line and
+/// column it points at and the source line with the offending text
+/// underlined, like
+///
+/// ```text
+/// [line 2] Error at '+': Operands must be two numbers or two strings.
+///  --> test.lox:2:9
+///   |
+/// 2 | print 1 + nil;
+///   |         ^
+/// ```
+///
+/// or, in the JSON format,
+///
+/// ```text
+/// {"severity":"runtime_error","message":"Operands must be two numbers or two strings.","file":"test.lox","line":2,"column":9,"span":{"start":19,"end":20}}
+/// ```
+pub(crate) struct Renderer {
+    color: bool,
+    format: ErrorFormat,
+}
+
+impl Renderer {
+    pub(crate) fn new(color: bool) -> Self {
+        Renderer { color, format: ErrorFormat::Human }
+    }
+
+    pub(crate) fn set_color(&mut self, color: bool) {
+        self.color = color;
+    }
+
+    pub(crate) fn set_format(&mut self, format: ErrorFormat) {
+        self.format = format;
+    }
+
+    /// `source` is the text named `name` that was being run.
+    pub(crate) fn render(&self, diagnostic: &Diagnostic, name: &str, source: &str) -> String {
+        match self.format {
+            ErrorFormat::Human => self.human(diagnostic, name, source),
+            ErrorFormat::Json => Self::json(diagnostic, name, source),
+        }
+    }
+
+    /// The location and snippet are left out when the diagnostic doesn't
+    /// point into `source`.
+    fn human(&self, diagnostic: &Diagnostic, name: &str, source: &str) -> String {
+        let message = diagnostic.to_string();
+        let (headline, rest) = message.split_once('\n').map_or((message.as_str(), None), |(h, r)| (h, Some(r)));
+
+        let mut s = self.paint(RED, headline);
+        if let Some(rest) = rest {
+            s.push('\n');
+            s.push_str(rest);
+        }
+
+        let span = match diagnostic {
+            Diagnostic::Scan(e) => Some(e.span),
+            d => d.token().and_then(|token| Self::token_span(source, token)),
+        };
+        if let Some(snippet) = span.and_then(|span| self.snippet(name, source, span)) {
+            s.push('\n');
+            s.push_str(&snippet);
+        }
+        s
+    }
+
+    /// A runtime error's stack trace, if any, is in `trace`, innermost call
+    /// first. `span` is `null` when the diagnostic doesn't point into `source`.
+    fn json(diagnostic: &Diagnostic, name: &str, source: &str) -> String {
+        let severity = match diagnostic.severity() {
+            Severity::Error => "error",
+            Severity::RuntimeError => "runtime_error",
+        };
+        let span = match diagnostic.token() {
+            Some(token) => Self::token_span(source, token),
+            None => Some(diagnostic.span()),
+        };
+        let span = span.map_or("null".to_string(), |Span { start, end }| {
+            format!("{{\"start\":{},\"end\":{}}}", start, end)
+        });
+
+        let mut s = format!(
+            "{{\"severity\":\"{}\",\"message\":{},\"file\":{},\"line\":{},\"column\":{},\"span\":{}",
+            severity,
+            json_string(diagnostic.message()),
+            json_string(name),
+            diagnostic.line(),
+            diagnostic.column(),
+            span,
+        );
+        if let Diagnostic::Runtime(e) = diagnostic {
+            if !e.trace.is_empty() {
+                let frames: Vec<String> = e.trace.iter()
+                    .map(|f| format!("{{\"function\":{},\"line\":{}}}", json_string(&f.function), f.line))
+                    .collect();
+                s.push_str(&format!(",\"trace\":[{}]", frames.join(",")));
+            }
+        }
+        s.push('}');
+        s
+    }
+
+    /// Returns `None` when the token didn't come from `source`, e.g. a runtime
+    /// error inside a function declared by an earlier REPL line.
+    fn token_span(source: &str, token: &Token) -> Option<Span> {
+        let Span { start, end } = token.span;
+        (source.get(start..end) == Some(token.lexeme.as_str())).then_some(token.span)
+    }
+
+    /// An empty span, such as the end of input, gets a single caret.
+    fn snippet(&self, name: &str, source: &str, span: Span) -> Option<String> {
+        let Span { start, end } = span;
+        source.get(start..end)?;
+
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..].find('\n').map_or(source.len(), |i| start + i);
+        let line = &source[line_start..line_end];
+
+        let line_number = (source[..start].matches('\n').count() + 1).to_string();
+        let column = source[line_start..start].chars().count() + 1;
+        let gutter = " ".repeat(line_number.len());
+
+        let padding: String = source[line_start..start].chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let width = source[start..end.min(line_end)].chars().count().max(1);
+
+        Some(format!(
+            "{gutter}{arrow} {name}:{line_number}:{column}\n{gutter} {bar}\n{number} {bar} {line}\n{gutter} {bar} {padding}{carets}",
+            arrow = self.paint(BLUE, "-->"),
+            bar = self.paint(BLUE, "|"),
+            number = self.paint(BLUE, &line_number),
+            line = line.trim_end(),
+            carets = self.paint(RED, &"^".repeat(width)),
+        ))
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Quotes `s` as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ParseError;
+    use crate::scanner::Scanner;
+
+    fn token(source: &str, lexeme: &str) -> Token {
+        let tokens = Scanner::new(source.to_string()).scan_tokens().unwrap();
+        tokens.into_iter().find(|t| t.lexeme == lexeme).unwrap()
+    }
+
+    #[test]
+    fn test_underlines_lexeme() {
+        let source = "var a = 1;\n\tprint a +  nil;\n";
+        let error = ParseError::new(&token(source, "nil"), "Expect number.");
+
+        assert_eq!(Renderer::new(false).render(&error.into(), "test.lox", source), [
+            "[line 2] Error at 'nil': Expect number.",
+            " --> test.lox:2:13",
+            "  |",
+            "2 | \tprint a +  nil;",
+            "  | \t           ^^^",
+        ].join("\n"));
+    }
+
+    #[test]
+    fn test_scan_errors() {
+        let source = "print \"a\\q\";\nprint \"open";
+        let errors = Scanner::new(source.to_string()).scan_tokens().unwrap_err();
+        let renderer = Renderer::new(false);
+
+        let rendered: Vec<String> = errors.into_iter().map(|e| renderer.render(&e.into(), "test.lox", source)).collect();
+        assert_eq!(rendered, vec![
+            "[line 1] Error: Invalid escape sequence '\\q'.\n --> test.lox:1:9\n  |\n1 | print \"a\\q\";\n  |         ^^",
+            "[line 2] Error: Unterminated string.\n --> test.lox:2:12\n  |\n2 | print \"open\n  |            ^",
+        ]);
+    }
+
+    #[test]
+    fn test_token_from_other_source_has_no_snippet() {
+        let error = ParseError::new(&token("print nil;", "nil"), "Expect number.");
+
+        assert_eq!(
+            Renderer::new(false).render(&error.into(), "<repl>", "other source"),
+            "[line 1] Error at 'nil': Expect number.",
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let mut renderer = Renderer::new(true);
+        renderer.set_format(ErrorFormat::Json);
+
+        let source = "print \"a\\q\";";
+        let error = Scanner::new(source.to_string()).scan_tokens().unwrap_err().remove(0);
+        assert_eq!(
+            renderer.render(&error.into(), "dir\\test.lox", source),
+            r#"{"severity":"error","message":"Invalid escape sequence '\\q'.","file":"dir\\test.lox","line":1,"column":9,"span":{"start":8,"end":10}}"#,
+        );
+
+        let error = ParseError::new(&token("print nil;", "nil"), "Expect number.");
+        assert_eq!(
+            renderer.render(&error.into(), "<repl>", "other source"),
+            r#"{"severity":"error","message":"Expect number.","file":"<repl>","line":1,"column":7,"span":null}"#,
+        );
+    }
+
+    #[test]
+    fn test_json_runtime_error_trace() {
+        let mut renderer = Renderer::new(false);
+        renderer.set_format(ErrorFormat::Json);
+
+        let source = "fun f() {\n  -nil;\n}\nf();";
+        let error = crate::lox::Lox::new().run_source(source).unwrap_err().into_diagnostics().remove(0);
+        assert_eq!(
+            renderer.render(&error, "t.lox", source),
+            r#"{"severity":"runtime_error","message":"Operand must be a number.","file":"t.lox","line":2,"column":3,"span":{"start":12,"end":13},"trace":[{"function":"f","line":4}]}"#,
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let source = "@";
+        let error = Scanner::new(source.to_string()).scan_tokens().unwrap_err().remove(0);
+
+        assert_eq!(Renderer::new(true).render(&error.into(), "a.lox", source), [
+            "\x1b[1;31m[line 1] Error: Unexpected character.\x1b[0m",
+            " \x1b[1;34m-->\x1b[0m a.lox:1:1",
+            "  \x1b[1;34m|\x1b[0m",
+            "\x1b[1;34m1\x1b[0m \x1b[1;34m|\x1b[0m @",
+            "  \x1b[1;34m|\x1b[0m \x1b[1;31m^\x1b[0m",
+        ].join("\n"));
+    }
+}
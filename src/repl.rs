@@ -0,0 +1,189 @@
+use crate::environment::Environment;
+use crate::line_editor::{LineEditor, ReadLine};
+use crate::lox::{Lox, Mode};
+use crate::scanner::Scanner;
+use crate::token::TokenType::*;
+
+/// What errors in code typed at the prompt say it came from.
+const REPL_NAME: &str = "<repl>";
+
+const HELP: &str = "\
+:help            Show this list
+:env             Show global variables and their values
+:tokens [code]   Show the tokens of code, or of the last input
+:ast [code]      Show the syntax tree of code, or of the last input
+:load <file>     Run a script in this session
+:reset           Forget everything defined in this session";
+
+/// The interactive prompt for a `Lox` session. Lines starting with `:` are
+/// commands to the REPL itself rather than Lox code; see `HELP`.
+pub(crate) struct Repl<'a> {
+    lox: &'a mut Lox,
+    editor: LineEditor,
+    last_input: String,
+    // The globals as they were before the first line, for `:reset`. This keeps
+    // natives defined by the host.
+    initial_globals: Environment,
+}
+
+impl<'a> Repl<'a> {
+    pub(crate) fn new(lox: &'a mut Lox) -> Self {
+        let initial_globals = lox.interpreter.globals.read().unwrap().clone();
+        Repl { lox, editor: LineEditor::new(), last_input: String::new(), initial_globals }
+    }
+
+    /// Input with unclosed brackets, strings or comments carries on over more
+    /// lines at a `..` prompt. Errors are printed and the session carries on.
+    /// Ctrl-C abandons the current input; Ctrl-D on an empty line exits.
+    pub(crate) fn run(&mut self) {
+        let mut input = String::new();
+        loop {
+            let prompt = if input.is_empty() { "> " } else { ".. " };
+            match self.editor.read_line(prompt) {
+                Ok(ReadLine::Line(line)) => {
+                    self.editor.add_history(&line);
+                    if input.is_empty() && line.starts_with(':') {
+                        self.command(&line);
+                        continue;
+                    }
+
+                    input.push_str(&line);
+                    input.push('\n');
+                    if !Self::is_incomplete(&input) {
+                        self.last_input = std::mem::take(&mut input);
+                        self.lox.run(REPL_NAME, &self.last_input, true);
+                    }
+                }
+                Ok(ReadLine::Interrupted) => input.clear(),
+                Ok(ReadLine::Eof) => break,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    break;
+                }
+            }
+        }
+    }
+
+    fn command(&mut self, line: &str) {
+        let (command, arg) = line.split_once(' ').map_or((line, ""), |(c, a)| (c, a.trim()));
+        let code = if arg.is_empty() { self.last_input.clone() } else { arg.to_string() };
+
+        match command {
+            ":help" => println!("{}", HELP),
+            ":env" => {
+                for binding in self.globals() {
+                    println!("{}", binding);
+                }
+            }
+            ":tokens" => self.lox.run_with_mode(REPL_NAME, &code, Mode::Tokens, true),
+            ":ast" => self.lox.run_with_mode(REPL_NAME, &code, Mode::Ast, true),
+            ":load" if !arg.is_empty() => match Lox::read_file(arg) {
+                Ok(source) => {
+                    self.lox.run(arg, &source, false);
+                    self.last_input = source;
+                }
+                Err(m) => eprintln!("{}", m),
+            },
+            ":load" => eprintln!("Usage: :load <file>"),
+            ":reset" => {
+                *self.lox.interpreter.globals.write().unwrap() = self.initial_globals.clone();
+                self.last_input.clear();
+            }
+            _ => eprintln!("Unknown command '{}'. Type :help for a list.", command),
+        }
+    }
+
+    /// `name = value` for each global, sorted by name.
+    fn globals(&self) -> Vec<String> {
+        let globals = self.lox.interpreter.globals.read().unwrap();
+        let mut bindings: Vec<String> = globals.bindings()
+            .map(|(name, value)| format!("{} = {:?}", name, value))
+            .collect();
+        bindings.sort();
+        bindings
+    }
+
+    /// Whether `input` stops partway through a construct, so the REPL should
+    /// read more lines before running it.
+    fn is_incomplete(input: &str) -> bool {
+        match Scanner::new(input.to_string()).scan_tokens() {
+            Ok(tokens) => {
+                let depth: i32 = tokens.iter()
+                    .map(|t| match t.token_type {
+                        LPAREN | LBRACE | LBRACKET => 1,
+                        RPAREN | RBRACE | RBRACKET => -1,
+                        _ => 0,
+                    })
+                    .sum();
+                depth > 0
+            }
+            Err(errors) => errors.iter().any(|e| e.is_unterminated()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_incomplete_input() {
+        assert!(Repl::is_incomplete("fun f() {\n"));
+        assert!(Repl::is_incomplete("fun f() {\n  if (a) {\n  }\n"));
+        assert!(Repl::is_incomplete("print (1 +\n"));
+        assert!(Repl::is_incomplete("print \"two\n"));
+        assert!(Repl::is_incomplete("print \"${1 +\n"));
+        assert!(Repl::is_incomplete("/* comment\n"));
+
+        assert!(!Repl::is_incomplete("fun f() {\n}\n"));
+        assert!(!Repl::is_incomplete("print 1 +\n"));
+        assert!(!Repl::is_incomplete("print 1);\n"));
+        assert!(!Repl::is_incomplete("print @;\n"));
+    }
+
+    /// Globals other than the built-in native functions.
+    fn user_globals(repl: &Repl) -> Vec<String> {
+        repl.globals().into_iter().filter(|g| !g.contains("<native fn")).collect()
+    }
+
+    #[test]
+    fn test_env_and_reset() {
+        let mut lox = Lox::new();
+        let mut repl = Repl::new(&mut lox);
+
+        repl.lox.run(REPL_NAME, "var b = \"two\"; var a = 1;", false);
+        assert_eq!(user_globals(&repl), vec!["a = 1", "b = \"two\""]);
+        assert!(repl.globals().contains(&"clock = <native fn clock>".to_string()));
+
+        repl.command(":reset");
+        assert!(user_globals(&repl).is_empty());
+        assert!(repl.globals().contains(&"clock = <native fn clock>".to_string()));
+    }
+
+    #[test]
+    fn test_session_survives_errors() {
+        let mut lox = Lox::new();
+        let repl = Repl::new(&mut lox);
+
+        repl.lox.run(REPL_NAME, "var a = 1;", true);
+        repl.lox.run(REPL_NAME, "var b = ;", true);
+        repl.lox.run(REPL_NAME, "var c = a + nil;", true);
+        repl.lox.run(REPL_NAME, "var d = a + 1;", true);
+
+        assert_eq!(user_globals(&repl), vec!["a = 1", "d = 2"]);
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("rlox_repl_load_{}.lox", std::process::id()));
+        std::fs::write(&path, "var loaded = 1 + 2;").unwrap();
+
+        let mut lox = Lox::new();
+        let mut repl = Repl::new(&mut lox);
+        repl.command(&format!(":load {}", path.display()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(repl.last_input, "var loaded = 1 + 2;");
+        assert!(repl.globals().contains(&"loaded = 3".to_string()));
+    }
+}
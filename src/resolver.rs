@@ -0,0 +1,475 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+
+use crate::class::conflict;
+use crate::error::ResolveError;
+use crate::expr::Expr;
+use crate::stmt::{FunctionDecl, Stmt};
+use crate::token::Token;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+    Trait,
+}
+
+/// Walks the AST once before execution, recording on each variable access
+/// how many scopes away its declaration is, and reporting static errors.
+pub(crate) struct Resolver {
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    /// The method names of each trait declared so far, to catch conflicts
+    /// between traits mixed into the same class.
+    traits: HashMap<String, Vec<String>>,
+    pub(crate) errors: Vec<ResolveError>,
+}
+
+impl Resolver {
+    pub(crate) fn new() -> Self {
+        Resolver {
+            scopes: vec![],
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            traits: HashMap::new(),
+            errors: vec![],
+        }
+    }
+
+    pub(crate) fn resolve(&mut self, statements: &[Stmt]) {
+        for s in statements {
+            self.resolve_stmt(s);
+        }
+    }
+
+    //Statements
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(statements) => {
+                self.begin_scope();
+                self.resolve(statements);
+                self.end_scope();
+            }
+            Stmt::Class { name, superclass, traits, fields, methods, class_methods } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                for (i, field) in fields.iter().enumerate() {
+                    if fields[..i].iter().any(|f| f.lexeme == field.lexeme) {
+                        self.error(field, "Already a field with this name in this class.");
+                    }
+                }
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable { name: super_name, .. } = superclass {
+                        if super_name.lexeme == name.lexeme {
+                            self.error(super_name, "A class can't inherit from itself.");
+                        }
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(superclass);
+
+                    self.begin_scope();
+                    self.scopes.last_mut().unwrap().insert("super".to_string(), true);
+                }
+
+                for t in traits {
+                    self.resolve_expr(t);
+                }
+                self.check_trait_conflicts(traits, methods);
+
+                self.begin_scope();
+                self.scopes.last_mut().unwrap().insert("this".to_string(), true);
+
+                for method in methods {
+                    let function_type = if method.name.lexeme == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.resolve_function(method, function_type);
+                }
+                // In a class method, `this` is the class.
+                for method in class_methods {
+                    self.resolve_function(method, FunctionType::Method);
+                }
+
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Trait { name, methods } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Trait;
+
+                self.declare(name);
+                self.define(name);
+                self.traits.insert(name.lexeme.clone(), methods.iter().map(|m| m.name.lexeme.clone()).collect());
+
+                self.begin_scope();
+                self.scopes.last_mut().unwrap().insert("this".to_string(), true);
+                // A mixed-in `init` initializes the class, so it follows the same rules.
+                for method in methods {
+                    let function_type = if method.name.lexeme == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+                    self.resolve_function(method, function_type);
+                }
+                self.end_scope();
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::DoWhile { body, condition } => {
+                self.resolve_stmt(body);
+                self.resolve_expr(condition);
+            }
+            Stmt::Destructure { names, initializer, .. } => {
+                for name in names {
+                    self.declare(name);
+                }
+                self.resolve_expr(initializer);
+                for name in names {
+                    self.define(name);
+                }
+            }
+            Stmt::Error(_) => (),
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Function(declaration) => {
+                self.declare(&declaration.name);
+                self.define(&declaration.name);
+                self.resolve_function(declaration, FunctionType::Function);
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::While { condition, body } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(body);
+            }
+        }
+    }
+
+    /// Only catches conflicts between traits declared in the code being
+    /// resolved. Others, like a trait declared by an earlier REPL line, are
+    /// checked when the class is created.
+    fn check_trait_conflicts(&mut self, traits: &[Expr], methods: &[Arc<FunctionDecl>]) {
+        let mut providers: HashMap<&str, &str> = HashMap::new();
+        let mut conflicts = vec![];
+
+        for t in traits {
+            let Expr::Variable { name, .. } = t else { continue };
+            let Some(trait_methods) = self.traits.get(&name.lexeme) else { continue };
+
+            for method in trait_methods.iter().filter(|m| !methods.iter().any(|own| &own.name.lexeme == *m)) {
+                if let Some(other) = providers.insert(method, &name.lexeme) {
+                    conflicts.push((name.clone(), conflict(method, other, &name.lexeme)));
+                }
+            }
+        }
+
+        for (name, message) in conflicts {
+            self.error(&name, &message);
+        }
+    }
+
+    fn resolve_function(&mut self, function: &FunctionDecl, function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+
+        self.begin_scope();
+        for param in &function.params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+    }
+
+    //Expressions
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign { name, value, depth } => {
+                self.resolve_expr(value);
+                self.resolve_local(name, depth);
+            }
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Call { callee, arguments, .. } => {
+                self.resolve_expr(callee);
+                for a in arguments {
+                    self.resolve_expr(a);
+                }
+            }
+            Expr::Conditional { condition, then_branch, else_branch } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            }
+            Expr::Function(declaration) => self.resolve_function(declaration, FunctionType::Function),
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Grouping(expr) => self.resolve_expr(expr),
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::Interpolation(parts) => {
+                for p in parts {
+                    self.resolve_expr(p);
+                }
+            }
+            Expr::List(elements) => {
+                for e in elements {
+                    self.resolve_expr(e);
+                }
+            }
+            Expr::Literal(_) => (),
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::SetIndex { object, index, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::Slice { object, start, end, .. } => {
+                self.resolve_expr(object);
+                for bound in [start, end].into_iter().flatten() {
+                    self.resolve_expr(bound);
+                }
+            }
+            Expr::Super { keyword, depth, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => self.error(keyword, "Can't use 'super' in a class with no superclass."),
+                    ClassType::Subclass => self.resolve_local(keyword, depth),
+                    ClassType::Trait => self.error(keyword, "Can't use 'super' in a trait."),
+                }
+            }
+            Expr::This { keyword, depth } => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+
+                self.resolve_local(keyword, depth);
+            }
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Update { target, .. } => self.resolve_expr(target),
+            Expr::Compound { target, value, .. } => {
+                self.resolve_expr(target);
+                self.resolve_expr(value);
+            }
+            Expr::Variable { name, depth } => {
+                if let Some(scope) = self.scopes.last() {
+                    if scope.get(&name.lexeme) == Some(&false) {
+                        self.error(name, "Can't read local variable in its own initializer.");
+                    }
+                }
+
+                self.resolve_local(name, depth);
+            }
+        }
+    }
+
+    //Helpers
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else { return; };
+
+        if scope.contains_key(&name.lexeme) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+
+        scope.insert(name.lexeme.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), true);
+        }
+    }
+
+    /// Leaves `depth` unset when the name isn't found in any local scope,
+    /// meaning it's a global.
+    fn resolve_local(&mut self, name: &Token, depth: &OnceLock<usize>) {
+        for (i, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                let _ = depth.set(i);
+                return;
+            }
+        }
+    }
+
+    fn error(&mut self, token: &Token, message: &str) {
+        self.errors.push(ResolveError::new(token, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(input: &str) -> (Vec<Stmt>, Vec<ResolveError>) {
+        let mut scanner = Scanner::new(input.to_string());
+        let stmts = Parser::new(scanner.scan_tokens().unwrap()).parse().unwrap();
+        let mut resolver = Resolver::new();
+        resolver.resolve(&stmts);
+        (stmts, resolver.errors)
+    }
+
+    fn messages(errors: Vec<ResolveError>) -> Vec<String> {
+        errors.into_iter().map(|e| e.message).collect()
+    }
+
+    #[test]
+    fn test_depths() {
+        let (stmts, errors) = resolve("var g; { var a; { a; g; } }");
+        assert!(errors.is_empty());
+
+        let Stmt::Block(outer) = &stmts[1] else { panic!() };
+        let Stmt::Block(inner) = &outer[1] else { panic!() };
+        let depth = |s: &Stmt| match s {
+            Stmt::Expression(Expr::Variable { depth, .. }) => depth.get().copied(),
+            _ => panic!("expected variable"),
+        };
+        assert_eq!(depth(&inner[0]), Some(1));
+        assert_eq!(depth(&inner[1]), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        let (_, errors) = resolve("{ var a = a; }");
+        assert_eq!(messages(errors), vec!["Can't read local variable in its own initializer."]);
+
+        let (_, errors) = resolve("fun f() { var a; var a; }");
+        assert_eq!(messages(errors), vec!["Already a variable with this name in this scope."]);
+
+        let (_, errors) = resolve("{ var [a, a] = [1, 2]; var [b] = [b]; }");
+        assert_eq!(messages(errors), vec![
+            "Already a variable with this name in this scope.",
+            "Can't read local variable in its own initializer.",
+        ]);
+
+        let (_, errors) = resolve("trait T { init() { return 1; } }");
+        assert_eq!(messages(errors), vec!["Can't return a value from an initializer."]);
+
+        let (_, errors) = resolve("trait T { m() { super.m(); } }");
+        assert_eq!(messages(errors), vec!["Can't use 'super' in a trait."]);
+
+        let (_, errors) = resolve("
+            trait A { m() {} n() {} }
+            trait B { n() {} }
+            trait C { m() {} }
+            class Ok with A, B { n() {} }
+            class Clash with A, B, C {}
+        ");
+        assert_eq!(messages(errors), vec![
+            "Method 'n' is provided by both 'A' and 'B'.",
+            "Method 'm' is provided by both 'A' and 'C'.",
+        ]);
+
+        let (_, errors) = resolve("class P { var x, y; var x; }");
+        assert_eq!(messages(errors), vec!["Already a field with this name in this class."]);
+
+        let (_, errors) = resolve("return 1;");
+        assert_eq!(errors[0].to_string(), "[line 1] Error at 'return': Can't return from top-level code.");
+    }
+
+    #[test]
+    fn test_class_errors() {
+        let (_, errors) = resolve("print this;");
+        assert_eq!(messages(errors), vec!["Can't use 'this' outside of a class."]);
+
+        let (_, errors) = resolve("fun f() { return this; }");
+        assert_eq!(messages(errors), vec!["Can't use 'this' outside of a class."]);
+
+        let (_, errors) = resolve("class A { init() { return 1; } }");
+        assert_eq!(messages(errors), vec!["Can't return a value from an initializer."]);
+
+        let (_, errors) = resolve("class A { init() { return; } method() { return this; } }");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_super_errors() {
+        let (_, errors) = resolve("class A < A {}");
+        assert_eq!(messages(errors), vec!["A class can't inherit from itself."]);
+
+        let (_, errors) = resolve("super.m();");
+        assert_eq!(messages(errors), vec!["Can't use 'super' outside of a class."]);
+
+        let (_, errors) = resolve("class A { m() { super.m(); } }");
+        assert_eq!(messages(errors), vec!["Can't use 'super' in a class with no superclass."]);
+
+        let (_, errors) = resolve("class A {} class B < A { m() { super.m(); } }");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_globals_may_be_redeclared() {
+        let (_, errors) = resolve("var a = 1; var a = a;");
+        assert!(errors.is_empty());
+    }
+}
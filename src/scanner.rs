@@ -1,38 +1,65 @@
-use std::any::Any;
 use std::collections::HashMap;
 
 use crate::token::TokenType;
 use crate::token::TokenType::*;
-use crate::token::Token;
-use crate::lox::Lox;
+use crate::token::{Literal, Span, Token};
+use crate::error::ScanError;
 
-pub(crate) struct Scanner {
+pub struct Scanner {
     input: String,
     tokens: Vec<Token>,
     start: usize,
     current: usize,
     line: u32,
+    line_start: usize,
+    keep_comments: bool,
+    // One entry per `${` we're inside, counting the `{`s opened since, so we
+    // know which `}` closes the interpolation.
+    interpolations: Vec<usize>,
+    errors: Vec<ScanError>,
 }
 
 impl Scanner {
-    pub(crate) fn new(input: String) -> Self {
+    pub fn new(input: String) -> Self {
         Scanner { 
-            input: input,
+            input,
             tokens: vec![],
             start: 0,
             current: 0,
-            line: 1, 
+            line: 1,
+            line_start: 0,
+            keep_comments: false,
+            interpolations: vec![],
+            errors: vec![],
         }
     }
 
-    pub(crate) fn scan_tokens(&mut self) -> &Vec<Token> {
+    /// A scanner that emits comments as `COMMENT` trivia tokens instead of
+    /// discarding them, for tools like formatters that need to keep them.
+    /// The parser does not accept these tokens.
+    pub fn new_with_comments(input: String) -> Self {
+        Scanner { keep_comments: true, ..Scanner::new(input) }
+    }
+
+    /// Scans the whole input. Scanning carries on past an error so that every
+    /// error in the input is reported, not just the first.
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<ScanError>> {
         while !self.is_at_end() {
             self.start = self.current;
             self.scan_token()
         }   
 
-        self.tokens.push(Token::new(EOF, "", self.line));
-        return &self.tokens;
+        if !self.interpolations.is_empty() {
+            self.unterminated("Unterminated string interpolation.");
+        }
+
+        self.start = self.current;
+        self.add_empty_token(EOF);
+
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+        Ok(std::mem::take(&mut self.tokens))
     }
 
     fn scan_token(&mut self) {
@@ -40,14 +67,66 @@ impl Scanner {
         match c {
             b'(' => self.add_empty_token(LPAREN),
             b')' => self.add_empty_token(RPAREN),
-            b'{' => self.add_empty_token(LBRACE),
-            b'}' => self.add_empty_token(RBRACE),
+            b'[' => self.add_empty_token(LBRACKET),
+            b']' => self.add_empty_token(RBRACKET),
+            b'{' => {
+                if let Some(depth) = self.interpolations.last_mut() {
+                    *depth += 1;
+                }
+                self.add_empty_token(LBRACE);
+            }
+            b'}' => match self.interpolations.last_mut() {
+                Some(0) => {
+                    self.interpolations.pop();
+                    self.string();
+                }
+                Some(depth) => {
+                    *depth -= 1;
+                    self.add_empty_token(RBRACE);
+                }
+                None => self.add_empty_token(RBRACE),
+            },
             b',' => self.add_empty_token(COMMA),
-            b'.' => self.add_empty_token(DOT),
-            b'-' => self.add_empty_token(MINUS),
-            b'+' => self.add_empty_token(PLUS),
+            b'.' => {
+                if self.match_two_char(b'.') {
+                    self.add_empty_token(DOTDOT);
+                } else {
+                    self.add_empty_token(DOT);
+                }
+            }
+            b'-' => {
+                if self.match_two_char(b'=') {
+                    self.add_empty_token(MINUSEQ);
+                } else if self.match_two_char(b'-') {
+                    self.add_empty_token(MINUSMINUS);
+                } else {
+                    self.add_empty_token(MINUS);
+                }
+            }
+            b'+' => {
+                if self.match_two_char(b'=') {
+                    self.add_empty_token(PLUSEQ);
+                } else if self.match_two_char(b'+') {
+                    self.add_empty_token(PLUSPLUS);
+                } else {
+                    self.add_empty_token(PLUS);
+                }
+            }
             b';' => self.add_empty_token(SEMICOLON),
-            b'*' => self.add_empty_token(STAR),
+            b'*' => {
+                if self.match_two_char(b'=') {
+                    self.add_empty_token(STAREQ);
+                } else {
+                    self.add_empty_token(STAR);
+                }
+            }
+            b'%' => self.add_empty_token(PERCENT),
+            b'&' => self.add_empty_token(AMP),
+            b'|' => self.add_empty_token(PIPE),
+            b'^' => self.add_empty_token(CARET),
+            b'~' => self.add_empty_token(TILDE),
+            b'?' => self.add_empty_token(QUESTION),
+            b':' => self.add_empty_token(COLON),
             b'!' => {
                 if self.match_two_char(b'=') {
                     self.add_empty_token(BANGEQ);
@@ -65,6 +144,8 @@ impl Scanner {
             b'<' => {
                 if self.match_two_char(b'=') {
                     self.add_empty_token(LTEQ);
+                } else if self.match_two_char(b'<') {
+                    self.add_empty_token(LTLT);
                 } else {
                     self.add_empty_token(LT);
                 }
@@ -72,35 +153,36 @@ impl Scanner {
             b'>' => {
                 if self.match_two_char(b'=') {
                     self.add_empty_token(GTEQ);
+                } else if self.match_two_char(b'>') {
+                    self.add_empty_token(GTGT);
                 } else {
                     self.add_empty_token(GT);
                 }
             }
             b'/' => {
                 if self.match_two_char(b'/') {
-                    while self.peek() != b'\n' && !self.is_at_end() {
-                        self.advance();
-                    }
+                    self.skip_until(b"\n");
+                    self.add_comment_token();
                 } else if self.match_two_char(b'*') {
-                    while self.peek() != b'*' && self.peek_next() != b'/' && !self.is_at_end() {
-                        self.advance();
-                    }
-                    self.advance();
-                    self.advance();
+                    self.block_comment();
+                } else if self.match_two_char(b'=') {
+                    self.add_empty_token(SLASHEQ);
                 } else {
                     self.add_empty_token(SLASH);
                 }
             }
             b'"' => self.string(),
-            b'\n' => self.line += 1,
-            b' ' | b'\t' | b'\r' => (),
+            b'\n' => self.newline(),
+            b' ' | b'\t' | b'\r' => self.skip_blanks(),
             _ => {
                 if Self::is_digit(c) {
                     self.number();
                 } else if Self::is_alpha(c){
                     self.ident();
+                } else if !c.is_ascii() {
+                    self.non_ascii();
                 } else {
-                    Lox::error(self.line, "Unexpected char")
+                    self.error("Unexpected character.")
                 }
             }
         }
@@ -110,78 +192,282 @@ impl Scanner {
     fn peek(&self) -> u8 {
         if self.is_at_end() { return b'\0'; }
 
-        return self.input.as_bytes()[self.current];
+        self.input.as_bytes()[self.current]
     }
 
     fn peek_next(&self) -> u8 {
         if self.current + 1 >= self.input.len() { return b'\0'; }
 
-        return self.input.as_bytes()[self.current + 1];
+        self.input.as_bytes()[self.current + 1]
     }
 
     fn advance(&mut self) -> u8 {
         let current = self.current;
         self.current += 1;
-        return self.input.as_bytes()[current];
+        self.input.as_bytes()[current]
+    }
+
+    /// Reports an error covering the current lexeme.
+    fn error(&mut self, m: &str) {
+        self.error_at(self.line, Span { start: self.start, end: self.current }, m);
+    }
+
+    /// Reports an error for input that ended too soon, at the end of input.
+    fn unterminated(&mut self, m: &str) {
+        self.error_at(self.line, Span { start: self.current, end: self.current }, m);
+    }
+
+    fn error_at(&mut self, line: u32, span: Span, m: &str) {
+        let mut error = ScanError::new(line, m);
+        error.column = self.column(span.start);
+        error.span = span;
+        self.errors.push(error);
     }
 
     fn is_at_end(&self) -> bool {
         self.current >= self.input.len()
     }
 
+    /// Moves `current` to the next occurrence of any of `bytes` (or the end of
+    /// input) in one pass over the remaining bytes, counting any newlines skipped.
+    fn skip_until(&mut self, bytes: &[u8]) {
+        let rest = &self.input.as_bytes()[self.current..];
+        let len = rest.iter().position(|c| bytes.contains(c)).unwrap_or(rest.len());
+        let skipped = &rest[..len];
+        self.line += skipped.iter().filter(|&&c| c == b'\n').count() as u32;
+        if let Some(last) = skipped.iter().rposition(|&c| c == b'\n') {
+            self.line_start = self.current + last + 1;
+        }
+        self.current += len;
+    }
+
+    /// Skips a run of spaces, tabs and carriage returns in one go.
+    fn skip_blanks(&mut self) {
+        let rest = &self.input.as_bytes()[self.current..];
+        let len = rest.iter().position(|&c| !matches!(c, b' ' | b'\t' | b'\r')).unwrap_or(rest.len());
+        self.current += len;
+    }
+
     fn add_empty_token(&mut self, t: TokenType) {
-        let text = &self.input[self.start..self.current];
-        let t = Token::new(t, text, self.line);
-        self.tokens.push(t);
+        self.add_token(t, Literal::Nil);
     }
 
-    fn add_token(&mut self, t: TokenType, literal: impl Any) {
+    fn add_comment_token(&mut self) {
+        if self.keep_comments {
+            self.add_empty_token(COMMENT);
+        }
+    }
+
+    fn add_token(&mut self, t: TokenType, literal: impl Into<Literal>) {
         let text = &self.input[self.start..self.current];
-        let t = Token::new_literal(t, text, literal, self.line);
+        let mut t = Token::new_literal(t, text, literal, self.line);
+        t.column = self.column(self.start);
+        t.span = Span { start: self.start, end: self.current };
         self.tokens.push(t)
     }
 
+    /// The 1-based column, in chars, of byte offset `at`. Tokens spanning
+    /// lines (strings, block comments) take their column from where they
+    /// start, which may be on an earlier line than `line_start`.
+    fn column(&self, at: usize) -> u32 {
+        let line_start = if at >= self.line_start {
+            self.line_start
+        } else {
+            self.input[..at].rfind('\n').map_or(0, |i| i + 1)
+        };
+        self.input[line_start..at].chars().count() as u32 + 1
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     fn match_two_char(&mut self, c: u8) -> bool {
         if self.is_at_end() { return false; }
         if self.input.as_bytes()[self.current] != c { return false; }
 
         self.current += 1;
-        return true;
+        true
     }
 
+    /// Scans a string from its opening `"`, or the rest of one from the `}`
+    /// that closes an interpolated expression. Stops at the closing `"`, or
+    /// at a `${`, leaving the embedded expression to be scanned as tokens.
     fn string(&mut self) {
-        while self.peek() != b'"' && !self.is_at_end() {
-            if self.peek() == b'\n' { self.line += 1; }
-            self.advance();
+        let start_line = self.line;
+
+        let token_type = loop {
+            self.skip_until(b"\"\\$");
+
+            if self.is_at_end() {
+                self.unterminated("Unterminated string.");
+                return;
+            }
+
+            match self.advance() {
+                b'"' => break STRING,
+                b'$' if self.match_two_char(b'{') => break INTERPOLATION,
+                b'$' => continue,
+                _ => (),
+            }
+
+            // Skip whatever follows a backslash so an escaped quote doesn't
+            // end the string; unescape() checks it's a valid escape.
+            if !self.is_at_end() && self.advance() == b'\n' {
+                self.newline();
+            }
+        };
+
+        let end = if token_type == INTERPOLATION {
+            self.interpolations.push(0);
+            self.current - 2
+        } else {
+            self.current - 1
+        };
+
+        let raw = &self.input[self.start+1..end];
+        match Self::unescape(raw) {
+            Ok(s) => self.add_token(token_type, s),
+            Err((offset, m)) => {
+                let line = start_line + raw[..offset].matches('\n').count() as u32;
+                // The backslash and the character after it.
+                let start = self.start + 1 + offset;
+                let end = start + 1 + raw[offset + 1..].chars().next().map_or(0, char::len_utf8);
+                self.error_at(line, Span { start, end }, &m);
+            }
         }
+    }
+
+    /// Decodes `\n`, `\t`, `\r`, `\0`, `\"`, `\\` and `\$`. On an invalid
+    /// escape, returns its byte offset in `raw` along with the error message.
+    fn unescape(raw: &str) -> Result<String, (usize, String)> {
+        let mut s = String::with_capacity(raw.len());
+        let mut chars = raw.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            if c != '\\' {
+                s.push(c);
+                continue;
+            }
 
-        if self.is_at_end() {
-            Lox::error(self.line, "Unterminated string.");
-            return;
+            match chars.next() {
+                Some((_, 'n')) => s.push('\n'),
+                Some((_, 't')) => s.push('\t'),
+                Some((_, 'r')) => s.push('\r'),
+                Some((_, '0')) => s.push('\0'),
+                Some((_, '"')) => s.push('"'),
+                Some((_, '\\')) => s.push('\\'),
+                Some((_, '$')) => s.push('$'),
+                Some((_, e)) => return Err((i, format!("Invalid escape sequence '\\{}'.", e))),
+                None => unreachable!("a terminated string never ends in a lone backslash"),
+            }
         }
 
-        self.advance();
+        Ok(s)
+    }
 
-        let s = &self.input[self.start+1..self.current-1];
-        self.add_token(STRING, s.to_string())
+    /// Block comments nest, so `/* a /* b */ c */` is a single comment.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.unterminated("Unterminated block comment.");
+                return;
+            }
+
+            match self.advance() {
+                b'/' if self.match_two_char(b'*') => depth += 1,
+                b'*' if self.match_two_char(b'/') => depth -= 1,
+                b'\n' => self.newline(),
+                _ => (),
+            }
+        }
+
+        self.add_comment_token();
     }
 
+    /// Decimal numbers, with an optional fraction and exponent, or hex (`0xFF`)
+    /// and binary (`0b1010`) integers. Digits may be separated by underscores.
     fn number(&mut self) {
-        while Self::is_digit(self.peek()) { self.advance(); }
+        let value = match (self.input.as_bytes()[self.start], self.peek()) {
+            (b'0', b'x' | b'X') => self.radix_number(16, "Expect hexadecimal digits after '0x'."),
+            (b'0', b'b' | b'B') => self.radix_number(2, "Expect binary digits after '0b'."),
+            _ => self.decimal_number(),
+        };
+
+        // A letter or digit straight after the literal, as in `0b12` or `1x`,
+        // makes the whole run one malformed literal rather than two tokens.
+        let trailing = Self::is_alpha(self.peek()) || Self::is_digit(self.peek());
+        while Self::is_alpha(self.peek()) || Self::is_digit(self.peek()) { self.advance(); }
+
+        match value {
+            Ok(_) if trailing => self.error("Invalid character in number literal."),
+            Ok(n) => self.add_token(NUM, n),
+            Err(m) => self.error(m),
+        }
+    }
+
+    fn decimal_number(&mut self) -> Result<f64, &'static str> {
+        self.digits(10)?;
 
         if self.peek() == b'.'  && Self::is_digit(self.peek_next()) {
             self.advance();
+            self.digits(10)?;
+        }
+
+        if matches!(self.peek(), b'e' | b'E') {
+            self.advance();
+            if matches!(self.peek(), b'+' | b'-') { self.advance(); }
+            if !Self::is_digit(self.peek()) { return Err("Expect digits in exponent."); }
+            self.digits(10)?;
+        }
+
+        let s = self.input[self.start..self.current].replace('_', "");
+        Ok(s.parse().unwrap())
+    }
+
+    /// Integers too big for an `f64` to hold exactly are rounded.
+    fn radix_number(&mut self, radix: u32, missing: &'static str) -> Result<f64, &'static str> {
+        self.advance();
+        if !(self.peek() as char).is_digit(radix) { return Err(missing); }
+        self.digits(radix)?;
+
+        let digits = &self.input[self.start + 2..self.current];
+        Ok(digits.chars().filter_map(|d| d.to_digit(radix)).fold(0.0, |n, d| n * radix as f64 + d as f64))
+    }
 
-            while Self::is_digit(self.peek()) { self.advance(); }
+    /// Consumes a run of digits in `radix`, starting at a digit. Underscores
+    /// may separate digits, one at a time, as in `1_000_000`.
+    fn digits(&mut self, radix: u32) -> Result<(), &'static str> {
+        loop {
+            match self.peek() {
+                b'_' if (self.peek_next() as char).is_digit(radix) => { self.advance(); }
+                b'_' => return Err("Digit separators must be between digits."),
+                c if (c as char).is_digit(radix) => { self.advance(); }
+                _ => return Ok(()),
+            }
         }
+    }
+
+    /// Input is a `String`, so it's valid UTF-8 and a non-ASCII byte starts a
+    /// multibyte char. Consume the whole char so lexemes never split one.
+    fn non_ascii(&mut self) {
+        let c = self.input[self.start..].chars().next().unwrap();
+        self.current = self.start + c.len_utf8();
 
-        let s = &self.input[self.start..self.current];
-        let n: f64 = s.parse().unwrap();
-        self.add_token(NUM, n)
+        if c.is_alphabetic() {
+            self.ident();
+        } else {
+            self.error("Unexpected character.");
+        }
     }
 
     fn ident(&mut self) {
-        while Self::is_alphanumeric(self.peek()) { self.advance(); }
+        while let Some(c) = self.input[self.current..].chars().next() {
+            if !(c == '_' || c.is_alphanumeric()) { break; }
+            self.current += c.len_utf8();
+        }
 
         let keywords = Self::keywords();
 
@@ -194,21 +480,18 @@ impl Scanner {
     }
 
     fn is_digit(c: u8) -> bool {
-        b'0' <= c && c <= b'9'
+        c.is_ascii_digit()
     }
 
     fn is_alpha(c: u8) -> bool {
-        c >= b'a' && c <= b'z' || c >= b'A' && c <= b'Z' || c == b'_'
-    }
-
-    fn is_alphanumeric(c: u8) -> bool {
-        Self::is_alpha(c) || Self::is_digit(c)
+        c.is_ascii_alphabetic() || c == b'_'
     }
 
     fn keywords() -> HashMap<String, TokenType> {
         HashMap::from([
             ("and".to_string(), AND),
             ("class".to_string(), CLASS),
+            ("do".to_string(), DO),
             ("else".to_string(), ELSE),
             ("false".to_string(), FALSE),
             ("for".to_string(), FOR),
@@ -220,21 +503,26 @@ impl Scanner {
             ("return".to_string(), RETURN),
             ("super".to_string(), SUPER),
             ("this".to_string(), THIS),
+            ("trait".to_string(), TRAIT),
             ("true".to_string(), TRUE),
             ("var".to_string(), VAR),
             ("while".to_string(), WHILE),
+            ("with".to_string(), WITH),
         ])
     }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
         
     #[test]
     fn test_scan_single_char_and_double_tokens() {
-        let input = "(){},.-+;*
+        let input = "(){},.-+;*%?:[]
         < > = /
-        <= >= == 
+        <= >= == ..
+        += -= *= /= ++ --
+        & | ^ ~ << >>
         // test a comment
         /* this is a c style comment 
         it can be multi line */
@@ -251,6 +539,11 @@ mod tests {
             Token::new(PLUS, "+", 1),
             Token::new(SEMICOLON, ";", 1),
             Token::new(STAR, "*", 1), 
+            Token::new(PERCENT, "%", 1),
+            Token::new(QUESTION, "?", 1),
+            Token::new(COLON, ":", 1),
+            Token::new(LBRACKET, "[", 1),
+            Token::new(RBRACKET, "]", 1),
             Token::new(LT, "<", 2), 
             Token::new(GT, ">", 2), 
             Token::new(EQ, "=", 2), 
@@ -258,11 +551,24 @@ mod tests {
             Token::new(LTEQ, "<=", 3), 
             Token::new(GTEQ, ">=", 3), 
             Token::new(EQEQ, "==", 3), 
-            Token::new(EOF, "", 6)
+            Token::new(DOTDOT, "..", 3),
+            Token::new(PLUSEQ, "+=", 4),
+            Token::new(MINUSEQ, "-=", 4),
+            Token::new(STAREQ, "*=", 4),
+            Token::new(SLASHEQ, "/=", 4),
+            Token::new(PLUSPLUS, "++", 4),
+            Token::new(MINUSMINUS, "--", 4),
+            Token::new(AMP, "&", 5),
+            Token::new(PIPE, "|", 5),
+            Token::new(CARET, "^", 5),
+            Token::new(TILDE, "~", 5),
+            Token::new(LTLT, "<<", 5),
+            Token::new(GTGT, ">>", 5),
+            Token::new(EOF, "", 9)
         ];
 
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
         for (i, e) in exp.into_iter().enumerate() {
             let t = &tokens[i];
             assert_eq!(e.token_type, t.token_type);
@@ -286,7 +592,7 @@ mod tests {
         ];
 
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
         for (i, e) in exp.into_iter().enumerate() {
             assert_eq!(e.line, tokens[i].line)
         }
@@ -299,12 +605,11 @@ mod tests {
         "#;
         
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
         let t = &tokens[0];
         assert_eq!(t.token_type, STRING);
         assert_eq!(t.lexeme, "\"this is a string\"");
-        let s: &String = t.literal.downcast_ref().unwrap();
-        assert_eq!(s, &"this is a string".to_string());
+        assert_eq!(t.literal, Literal::Str("this is a string".into()));
     } 
 
     #[test]
@@ -313,6 +618,8 @@ mod tests {
         34 69 420
         6.9
         42.0
+        1..3
+        0xFF 0b1010 1e9 2.5e-3 1E+2 1_000_000 0xdead_BEEF
         ";
 
         let exp = vec![
@@ -322,19 +629,27 @@ mod tests {
             Token::new_literal(NUM, "420", 420.0, 2),
             Token::new_literal(NUM, "6.9", 6.9, 3),
             Token::new_literal(NUM, "42.0", 42.0, 4),
+            Token::new_literal(NUM, "1", 1.0, 5),
+            Token::new(DOTDOT, "..", 5),
+            Token::new_literal(NUM, "3", 3.0, 5),
+            Token::new_literal(NUM, "0xFF", 255.0, 6),
+            Token::new_literal(NUM, "0b1010", 10.0, 6),
+            Token::new_literal(NUM, "1e9", 1e9, 6),
+            Token::new_literal(NUM, "2.5e-3", 2.5e-3, 6),
+            Token::new_literal(NUM, "1E+2", 100.0, 6),
+            Token::new_literal(NUM, "1_000_000", 1e6, 6),
+            Token::new_literal(NUM, "0xdead_BEEF", 3735928559.0, 6),
         ];
 
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
 
         for (i, e) in exp.into_iter().enumerate() {
             let t = &tokens[i];
-            assert_eq!(e.token_type, NUM);
+            assert_eq!(e.token_type, t.token_type);
             assert_eq!(e.lexeme, t.lexeme);
             assert_eq!(e.line, t.line);
-            let n: &f64 = t.literal.downcast_ref().unwrap();
-            let en: &f64 = e.literal.downcast_ref().unwrap();
-            assert_eq!(n, en);
+            assert_eq!(e.literal, t.literal);
         }
     }
 
@@ -350,26 +665,25 @@ mod tests {
         ];
 
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
 
         for (i, e) in exp.into_iter().enumerate() {
             let t = &tokens[i];
             assert_eq!(e.token_type, IDENT);
             assert_eq!(e.lexeme, t.lexeme);
             assert_eq!(e.line, t.line);
-            let ident: &String = t.literal.downcast_ref().unwrap();
-            let eident: &String = e.literal.downcast_ref().unwrap();
-            assert_eq!(eident, ident);
+            assert_eq!(e.literal, t.literal);
         }
     }
 
     #[test]
     fn test_keywords() {
-        let input = "and class else false for fun if nil or print return super this true var while";
+        let input = "and class do else false for fun if nil or print return super this trait true var while with";
         
         let exp = vec![
             Token::new(AND, "and", 1),
             Token::new(CLASS, "class", 1),
+            Token::new(DO, "do", 1),
             Token::new(ELSE, "else", 1),
             Token::new(FALSE, "false", 1),
             Token::new(FOR, "for", 1),
@@ -381,14 +695,16 @@ mod tests {
             Token::new(RETURN, "return", 1),
             Token::new(SUPER, "super", 1),
             Token::new(THIS, "this", 1),
+            Token::new(TRAIT, "trait", 1),
             Token::new(TRUE, "true", 1),
             Token::new(VAR, "var", 1),
             Token::new(WHILE, "while", 1),
+            Token::new(WITH, "with", 1),
             Token::new(EOF, "", 1),
         ];
 
         let mut s = Scanner::new(input.to_string());
-        let tokens = s.scan_tokens();
+        let tokens = s.scan_tokens().unwrap();
 
         for (i, e) in exp.into_iter().enumerate() {
             let t = &tokens[i];
@@ -397,4 +713,302 @@ mod tests {
             assert_eq!(e.line, t.line);
         }
     }
+
+    #[test]
+    fn test_skipping_comments_and_strings() {
+        let input = "// a comment with \"quotes\" and ( parens
+        \"a string
+        over lines\"      ;   // trailing
+        ident";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        assert_eq!(tokens[0].token_type, STRING);
+        assert_eq!(tokens[0].line, 3);
+        assert_eq!(tokens[1].token_type, SEMICOLON);
+        assert_eq!(tokens[1].line, 3);
+        assert_eq!(tokens[2].token_type, IDENT);
+        assert_eq!(tokens[2].line, 4);
+        assert_eq!(tokens[3].token_type, EOF);
+    }
+
+    #[test]
+    fn test_line_comments_end_at_newline() {
+        let input = "// comment
+        a // trailing comment
+        //
+        b";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let exp = vec![
+            Token::new(IDENT, "a", 2),
+            Token::new(IDENT, "b", 4),
+            Token::new(EOF, "", 4),
+        ];
+        assert_eq!(tokens, exp);
+    }
+
+    #[test]
+    fn test_comments_as_trivia() {
+        let input = "// leading
+        a // trailing";
+
+        let mut s = Scanner::new_with_comments(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let exp = vec![
+            Token::new(COMMENT, "// leading", 1),
+            Token::new(IDENT, "a", 2),
+            Token::new(COMMENT, "// trailing", 2),
+            Token::new(EOF, "", 2),
+        ];
+        assert_eq!(tokens, exp);
+    }
+
+    #[test]
+    fn test_block_comments() {
+        let input = "a /* one line */ b
+        /* spans
+           lines */ c
+        /* outer /* inner
+           */ still comment */ d
+        /**/ e /***/ f";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let exp = vec![
+            Token::new(IDENT, "a", 1),
+            Token::new(IDENT, "b", 1),
+            Token::new(IDENT, "c", 3),
+            Token::new(IDENT, "d", 5),
+            Token::new(IDENT, "e", 6),
+            Token::new(IDENT, "f", 6),
+            Token::new(EOF, "", 6),
+        ];
+        assert_eq!(tokens, exp);
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let input = "a /* never
+        /* closed */";
+
+        let mut s = Scanner::new(input.to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        assert_eq!(errors, vec![ScanError::new(2, "Unterminated block comment.")]);
+    }
+
+    #[test]
+    fn test_block_comments_as_trivia() {
+        let mut s = Scanner::new_with_comments("/* a /* b */ */ x".to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        assert_eq!(tokens[0], Token::new(COMMENT, "/* a /* b */ */", 1));
+        assert_eq!(tokens[1], Token::new(IDENT, "x", 1));
+    }
+
+    #[test]
+    fn test_columns_and_spans() {
+        let input = "var x = 10;
+  print \"a
+b\" + x;";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let positions: Vec<(u32, u32, usize, usize)> = tokens.iter()
+            .map(|t| (t.line, t.column, t.span.start, t.span.end))
+            .collect();
+        assert_eq!(positions, vec![
+            (1, 1, 0, 3),
+            (1, 5, 4, 5),
+            (1, 7, 6, 7),
+            (1, 9, 8, 10),
+            (1, 11, 10, 11),
+            (2, 3, 14, 19),
+            (3, 9, 20, 25),
+            (3, 4, 26, 27),
+            (3, 6, 28, 29),
+            (3, 7, 29, 30),
+            (3, 8, 30, 30),
+        ]);
+        assert_eq!(&input[tokens[5].span.start..tokens[5].span.end], "print");
+    }
+
+    #[test]
+    fn test_non_ascii_strings() {
+        let input = "\"héllo wörld 🦀\" \"日本語\"";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        assert_eq!(tokens[0].literal, Literal::Str("héllo wörld 🦀".into()));
+        assert_eq!(tokens[1].literal, Literal::Str("日本語".into()));
+        assert_eq!(tokens[1].column, 17);
+    }
+
+    #[test]
+    fn test_non_ascii_identifiers() {
+        let input = "var café = ñandú_2 + π;";
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let exp = vec![
+            Token::new(VAR, "var", 1),
+            Token::new(IDENT, "café", 1),
+            Token::new(EQ, "=", 1),
+            Token::new(IDENT, "ñandú_2", 1),
+            Token::new(PLUS, "+", 1),
+            Token::new(IDENT, "π", 1),
+            Token::new(SEMICOLON, ";", 1),
+            Token::new(EOF, "", 1),
+        ];
+        assert_eq!(tokens, exp);
+        assert_eq!(tokens[3].column, 12);
+        assert_eq!(tokens[4].column, 20);
+    }
+
+    #[test]
+    fn test_non_ascii_symbols_are_single_errors() {
+        let mut s = Scanner::new("a → b".to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        assert_eq!(errors, vec![ScanError::new(1, "Unexpected character.")]);
+    }
+
+    #[test]
+    fn test_escape_sequences() {
+        let input = r#""tab\there" "line\nbreak" "quote \"inside\"" "back\\slash" "\r\0""#;
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let literals: Vec<Literal> = tokens.into_iter().take(5).map(|t| t.literal).collect();
+        assert_eq!(literals, vec![
+            Literal::Str("tab\there".into()),
+            Literal::Str("line\nbreak".into()),
+            Literal::Str("quote \"inside\"".into()),
+            Literal::Str("back\\slash".into()),
+            Literal::Str("\r\0".into()),
+        ]);
+    }
+
+    #[test]
+    fn test_escaped_quote_keeps_raw_lexeme() {
+        let mut s = Scanner::new(r#""say \"hi\"" x"#.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        assert_eq!(tokens[0].lexeme, r#""say \"hi\"""#);
+        assert_eq!(tokens[1], Token::new(IDENT, "x", 1));
+    }
+
+    #[test]
+    fn test_invalid_escape_sequences() {
+        let input = r#""fine"
+        "multi
+        bad \q"
+        "unterminated \""#;
+
+        let mut s = Scanner::new(input.to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        assert_eq!(errors, vec![
+            ScanError::new(3, "Invalid escape sequence '\\q'."),
+            ScanError::new(4, "Unterminated string."),
+        ]);
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let input = r#""a ${x} b ${ {y} } c" "$5 \${z}""#;
+
+        let mut s = Scanner::new(input.to_string());
+        let tokens = s.scan_tokens().unwrap();
+
+        let exp = vec![
+            Token::new_literal(INTERPOLATION, "\"a ${", "a ".to_string(), 1),
+            Token::new(IDENT, "x", 1),
+            Token::new_literal(INTERPOLATION, "} b ${", " b ".to_string(), 1),
+            Token::new(LBRACE, "{", 1),
+            Token::new(IDENT, "y", 1),
+            Token::new(RBRACE, "}", 1),
+            Token::new_literal(STRING, "} c\"", " c".to_string(), 1),
+            Token::new_literal(STRING, r#""$5 \${z}""#, "$5 ${z}".to_string(), 1),
+            Token::new(EOF, "", 1),
+        ];
+        assert_eq!(tokens, exp);
+    }
+
+    #[test]
+    fn test_nested_interpolation() {
+        let mut s = Scanner::new(r#""${ "in${x}" }""#.to_string());
+        let types: Vec<TokenType> = s.scan_tokens().unwrap().into_iter().map(|t| t.token_type).collect();
+
+        assert_eq!(types, vec![INTERPOLATION, INTERPOLATION, IDENT, STRING, STRING, EOF]);
+    }
+
+    #[test]
+    fn test_unterminated_interpolation() {
+        let mut s = Scanner::new("\"a ${x".to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        assert_eq!(errors, vec![ScanError::new(1, "Unterminated string interpolation.")]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = "@
+        \"unterminated";
+
+        let mut s = Scanner::new(input.to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        assert_eq!(errors, vec![
+            ScanError::new(1, "Unexpected character."),
+            ScanError::new(2, "Unterminated string."),
+        ]);
+        assert_eq!(errors[0].to_string(), "[line 1] Error: Unexpected character.");
+    }
+
+    #[test]
+    fn test_malformed_numbers() {
+        let input = "0x; 0b2 0b102 1e 2.5e+x 1_ 1__0 12abc 0x_1";
+
+        let mut s = Scanner::new(input.to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        let errors: Vec<(&str, &str)> = errors.iter()
+            .map(|e| (&input[e.span.start..e.span.end], e.message.as_str()))
+            .collect();
+        assert_eq!(errors, vec![
+            ("0x", "Expect hexadecimal digits after '0x'."),
+            ("0b2", "Expect binary digits after '0b'."),
+            ("0b102", "Invalid character in number literal."),
+            ("1e", "Expect digits in exponent."),
+            ("2.5e+x", "Expect digits in exponent."),
+            ("1_", "Digit separators must be between digits."),
+            ("1__0", "Digit separators must be between digits."),
+            ("12abc", "Invalid character in number literal."),
+            ("0x_1", "Expect hexadecimal digits after '0x'."),
+        ]);
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let input = "a @ \"b \\q\" →\n\"open";
+
+        let mut s = Scanner::new(input.to_string());
+        let errors = s.scan_tokens().unwrap_err();
+
+        let positions: Vec<(u32, u32, &str)> = errors.iter()
+            .map(|e| (e.line, e.column, &input[e.span.start..e.span.end]))
+            .collect();
+        assert_eq!(positions, vec![(1, 3, "@"), (1, 8, "\\q"), (1, 12, "→"), (2, 6, "")]);
+    }
 }
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::expr::Expr;
+use crate::token::Token;
+use crate::token::TokenType::FUN;
+
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum Stmt {
+    Block(Vec<Stmt>),
+    /// `class_methods` are declared with `class` in the body and called on the
+    /// class itself. `traits` are the names after `with`, and `fields` those
+    /// declared with `var`.
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        traits: Vec<Expr>,
+        fields: Vec<Token>,
+        methods: Vec<Arc<FunctionDecl>>,
+        class_methods: Vec<Arc<FunctionDecl>>,
+    },
+    /// `do body while (condition);`: the body runs before the first test.
+    DoWhile { body: Box<Stmt>, condition: Expr },
+    /// `var [a, b] = initializer;`. `bracket` is the closing bracket of the
+    /// names, for errors.
+    Destructure { names: Vec<Token>, bracket: Token, initializer: Expr },
+    /// The tokens of a statement that failed to parse, kept in place of it so
+    /// a partial tree still shows everything around the error.
+    Error(Vec<Token>),
+    Expression(Expr),
+    Function(Arc<FunctionDecl>),
+    If { condition: Expr, then_branch: Box<Stmt>, else_branch: Option<Box<Stmt>> },
+    Print(Expr),
+    Return { keyword: Token, value: Option<Expr> },
+    /// A bundle of methods that classes can mix in with `with`.
+    Trait { name: Token, methods: Vec<Arc<FunctionDecl>> },
+    Var { name: Token, initializer: Option<Expr> },
+    While { condition: Expr, body: Box<Stmt> },
+}
+
+/// A function's name, parameters and body, shared between the AST and the
+/// runtime functions created from it. An anonymous function's `name` is its
+/// `fun` keyword.
+#[derive(Debug, PartialEq)]
+pub(crate) struct FunctionDecl {
+    pub(crate) name: Token,
+    pub(crate) params: Vec<Token>,
+    pub(crate) body: Vec<Stmt>,
+    /// A method declared without a parameter list, `area { ... }`, which runs
+    /// when the property is read.
+    pub(crate) is_getter: bool,
+}
+
+impl FunctionDecl {
+    pub(crate) fn is_anonymous(&self) -> bool {
+        self.name.token_type == FUN
+    }
+}
@@ -1,37 +1,152 @@
-use std::any::Any;
+use std::fmt;
+use std::sync::Arc;
 
+use crate::value::format_number;
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone, Copy)]
-pub(crate) enum TokenType {
-    LPAREN, RPAREN, LBRACE, RBRACE, COMMA, DOT, MINUS, PLUS, SEMICOLON, SLASH, STAR,
+pub enum TokenType {
+    LPAREN, RPAREN, LBRACE, RBRACE, COMMA, DOT, MINUS, PLUS, SEMICOLON, SLASH, STAR, PERCENT,
+    QUESTION, COLON, LBRACKET, RBRACKET, AMP, PIPE, CARET, TILDE,
 
-    BANG, BANGEQ, EQ, EQEQ, GT, LT, GTEQ, LTEQ, 
+    BANG, BANGEQ, EQ, EQEQ, GT, LT, GTEQ, LTEQ, LTLT, GTGT, DOTDOT,
+    PLUSEQ, MINUSEQ, STAREQ, SLASHEQ, PLUSPLUS, MINUSMINUS,
 
     IDENT, STRING, NUM,
 
-    AND, CLASS, ELSE, FALSE, FUN, FOR, IF, NIL, OR, PRINT, RETURN, SUPER, THIS, TRUE, VAR, WHILE,
+    // The part of an interpolated string up to and including `${`, or between
+    // a `}` and the next `${`. The part after the last `}` is a `STRING`.
+    INTERPOLATION,
+
+    AND, CLASS, DO, ELSE, FALSE, FUN, FOR, IF, NIL, OR, PRINT, RETURN, SUPER, THIS, TRAIT, TRUE, VAR, WHILE, WITH,
+
+    // Only produced by a scanner created with `Scanner::new_with_comments`.
+    COMMENT,
 
     EOF 
 }
 
-#[derive(Debug)]
-pub(crate) struct Token {
-    pub(crate) token_type: TokenType,
-    pub(crate) lexeme: String,
-    pub(crate) literal: Box<dyn Any>,
-    pub(crate) line: u32,
+/// Prints the variant name, padded when a width is given, for token tables.
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("{:?}", self))
+    }
+}
+
+/// The value carried by a literal token, and by literal expressions once parsed.
+/// Tokens without a literal value carry `Nil`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Literal {
+    Num(f64),
+    Str(Arc<str>),
+    Bool(bool),
+    Nil,
+}
+
+impl From<f64> for Literal {
+    fn from(n: f64) -> Self {
+        Literal::Num(n)
+    }
+}
+
+impl From<String> for Literal {
+    fn from(s: String) -> Self {
+        Literal::Str(s.into())
+    }
+}
+
+/// Strings are quoted and escaped, so `"1"` and `1` read differently.
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Num(n) => write!(f, "{}", format_number(*n)),
+            Literal::Str(s) => write!(f, "{:?}", s),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+/// Byte offsets of a token's lexeme in the source, `start..end`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub token_type: TokenType,
+    pub lexeme: String,
+    pub literal: Literal,
+    pub line: u32,
+    pub column: u32,
+    pub span: Span,
+}
+
+/// Tokens compare by type, lexeme, literal and line only, so tokens built by
+/// hand in tests match scanned ones without spelling out columns and spans.
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+            && self.line == other.line
+    }
 }
 
 impl Token {
-    pub(crate) fn new_literal(t: TokenType, lexeme: &str, literal: impl Any, line: u32) -> Self {
+    pub(crate) fn new_literal(t: TokenType, lexeme: &str, literal: impl Into<Literal>, line: u32) -> Self {
         Token {
             token_type: t,
             lexeme: lexeme.to_string(),
-            literal: Box::new(literal),
-            line: line,
+            literal: literal.into(),
+            line,
+            column: 0,
+            span: Span::default(),
         }
     }
 
     pub(crate) fn new(t: TokenType, lexeme: &str, line: u32) -> Self {
-        Token { token_type: t, lexeme: lexeme.to_string(), literal: Box::new("".to_string()), line: line }
+        Token::new_literal(t, lexeme, Literal::Nil, line)
+    }
+}
+
+impl Token {
+    /// The header row for a table of tokens printed with `Display`.
+    pub(crate) const TABLE_HEADER: &'static str = "LINE:COL  TYPE           LEXEME               LITERAL";
+}
+
+/// One row of a token table: position, type, lexeme and the literal value if
+/// the token carries one. Line breaks and tabs in the lexeme are escaped to
+/// keep rows on one line and aligned.
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = format!("{}:{}", self.line, self.column);
+        let literal = if self.literal == Literal::Nil { String::new() } else { self.literal.to_string() };
+        let lexeme = self.lexeme.replace('\n', "\\n").replace('\r', "\\r").replace('\t', "\\t");
+        let row = format!("{:<9} {:<14} {:<20} {}", position, self.token_type, lexeme, literal);
+        f.write_str(row.trim_end())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::TokenType::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(format!("[{:<6}]", EQEQ), "[EQEQ  ]");
+        assert_eq!(Literal::Str("a\n".into()).to_string(), "\"a\\n\"");
+        assert_eq!(Literal::Num(1.5).to_string(), "1.5");
+
+        let mut t = Token::new(PRINT, "print", 3);
+        t.column = 5;
+        assert_eq!(t.to_string(), "3:5       PRINT          print");
+
+        let mut t = Token::new_literal(STRING, "\"a\nb\"", "a\nb".to_string(), 1);
+        t.column = 12;
+        assert_eq!(t.to_string(), "1:12      STRING         \"a\\nb\"               \"a\\nb\"");
+    }
+}
@@ -0,0 +1,172 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance, LoxTrait};
+use crate::map::LoxMap;
+use crate::token::Literal;
+
+#[derive(Clone)]
+pub enum Value {
+    Number(f64),
+    /// Shared, so copying a string value never copies its text.
+    Str(Arc<str>),
+    Bool(bool),
+    Nil,
+    Callable(Arc<dyn LoxCallable>),
+    Class(Arc<LoxClass>),
+    Instance(Arc<RwLock<LoxInstance>>),
+    Trait(Arc<LoxTrait>),
+    List(Arc<RwLock<Vec<Value>>>),
+    Map(Arc<RwLock<LoxMap>>),
+}
+
+impl Value {
+    /// `nil` and `false` are falsey, everything else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// Formats the value as `Display` does, except that `instance` formats
+    /// any instance value, whether it's this value or inside a list or map.
+    /// Containers are copied before their elements are formatted, so
+    /// `instance` may run Lox code that changes them.
+    pub(crate) fn format_with<E>(
+        &self,
+        instance: &mut dyn FnMut(&Value) -> Result<String, E>,
+    ) -> Result<String, E> {
+        // Strings inside containers are quoted.
+        let mut element = |value: &Value| match value {
+            Value::Str(s) => Ok(format!("{:?}", s)),
+            _ => value.format_with(instance),
+        };
+
+        match self {
+            Value::Instance(_) => instance(self),
+            Value::List(list) => {
+                let Some(_guard) = PrintGuard::enter(Arc::as_ptr(list) as *const ()) else {
+                    return Ok("[...]".to_string());
+                };
+                let list = list.read().unwrap().clone();
+                let elements = list.iter().map(&mut element).collect::<Result<Vec<_>, E>>()?;
+                Ok(format!("[{}]", elements.join(", ")))
+            }
+            Value::Map(map) => {
+                let Some(_guard) = PrintGuard::enter(Arc::as_ptr(map) as *const ()) else {
+                    return Ok("{...}".to_string());
+                };
+                let entries: Vec<(String, Value)> = map.read().unwrap().entries()
+                    .map(|(k, v)| (k.describe(), v.clone()))
+                    .collect();
+                let entries = entries.iter()
+                    .map(|(k, v)| Ok(format!("{}: {}", k, element(v)?)))
+                    .collect::<Result<Vec<_>, E>>()?;
+                Ok(format!("{{{}}}", entries.join(", ")))
+            }
+            _ => Ok(self.to_string()),
+        }
+    }
+}
+
+impl From<&Literal> for Value {
+    fn from(literal: &Literal) -> Self {
+        match literal {
+            Literal::Num(n) => Value::Number(*n),
+            Literal::Str(s) => Value::Str(s.clone()),
+            Literal::Bool(b) => Value::Bool(*b),
+            Literal::Nil => Value::Nil,
+        }
+    }
+}
+
+/// Callables, classes, instances, traits, lists and maps are only equal to
+/// themselves.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Number(l), Value::Number(r)) => l == r,
+            (Value::Str(l), Value::Str(r)) => Arc::ptr_eq(l, r) || l == r,
+            (Value::Bool(l), Value::Bool(r)) => l == r,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Callable(l), Value::Callable(r)) => Arc::ptr_eq(l, r),
+            (Value::Class(l), Value::Class(r)) => Arc::ptr_eq(l, r),
+            (Value::Instance(l), Value::Instance(r)) => Arc::ptr_eq(l, r),
+            (Value::Trait(l), Value::Trait(r)) => Arc::ptr_eq(l, r),
+            (Value::List(l), Value::List(r)) => Arc::ptr_eq(l, r),
+            (Value::Map(l), Value::Map(r)) => Arc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Str(s) => write!(f, "{:?}", s),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+thread_local! {
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(vec![]) };
+}
+
+/// Marks a container as being printed, so one that contains itself prints
+/// the inner occurrence as `[...]` or `{...}` instead of recursing forever.
+struct PrintGuard(*const ());
+
+impl PrintGuard {
+    /// Returns `None` if `ptr` is already being printed further up.
+    fn enter(ptr: *const ()) -> Option<Self> {
+        PRINTING.with(|printing| {
+            let mut printing = printing.borrow_mut();
+            if printing.contains(&ptr) {
+                return None;
+            }
+            printing.push(ptr);
+            Some(PrintGuard(ptr))
+        })
+    }
+}
+
+impl Drop for PrintGuard {
+    fn drop(&mut self) {
+        PRINTING.with(|printing| printing.borrow_mut().retain(|&p| p != self.0));
+    }
+}
+
+/// Formats a number the way Lox prints it: integers without a trailing `.0`,
+/// and the infinities as `Infinity` and `-Infinity` rather than Rust's `inf`.
+pub(crate) fn format_number(n: f64) -> String {
+    if n.is_infinite() {
+        return if n > 0.0 { "Infinity".to_string() } else { "-Infinity".to_string() };
+    }
+
+    n.to_string()
+}
+
+/// How values print in Lox, e.g. with `print`: strings without quotes, numbers
+/// as `format_number` gives them, functions as `<fn name>`, instances as
+/// `Name instance`, traits as `Name trait`, lists as `[1, "two", nil]` and maps as `{"a": 1}`.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", format_number(*n)),
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Nil => write!(f, "nil"),
+            Value::Callable(c) => write!(f, "{}", c.to_string()),
+            Value::Class(c) => write!(f, "{}", c.name),
+            Value::Trait(t) => write!(f, "{} trait", t.name),
+            Value::Instance(_) | Value::List(_) | Value::Map(_) => {
+                let s = self.format_with(&mut |value| match value {
+                    Value::Instance(i) => Ok::<_, fmt::Error>(format!("{} instance", i.read().unwrap().class.name)),
+                    _ => unreachable!("only instances are passed in"),
+                })?;
+                write!(f, "{}", s)
+            }
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A scratch directory for one test, removed when dropped.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("rlox_cli_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn rlox(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_rlox")).args(args).output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_runs_script() {
+    let dir = TempDir::new("runs_script");
+    let script = dir.file("ok.lox", "print 1 + 2;");
+
+    let output = rlox(&[script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "3\n");
+}
+
+#[test]
+fn test_math_script() {
+    let dir = TempDir::new("math_script");
+    let script = dir.file("math.lox", "
+        fun hypot(a, b) { return sqrt(pow(a, 2) + pow(b, 2)); }
+        print hypot(3, 4);
+        print floor(7 / 2) + ceil(0.1);
+        print max(abs(-2), min(5, 3));
+        var r = floor(random() * 6) + 1;
+        print r >= 1 and r <= 6;
+    ");
+
+    let output = rlox(&[script.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "5\n4\n3\ntrue\n");
+}
+
+#[test]
+fn test_fizzbuzz() {
+    let dir = TempDir::new("fizzbuzz");
+    let script = dir.file("fizzbuzz.lox", "
+        for (var i = 1; i <= 15; i = i + 1) {
+            if (i % 15 == 0) print \"FizzBuzz\";
+            else if (i % 3 == 0) print \"Fizz\";
+            else if (i % 5 == 0) print \"Buzz\";
+            else print i;
+        }
+    ");
+
+    let output = rlox(&[script.to_str().unwrap()]);
+    assert_eq!(stdout(&output), "1\n2\nFizz\n4\nBuzz\nFizz\n7\n8\nFizz\nBuzz\n11\nFizz\n13\n14\nFizzBuzz\n");
+}
+
+#[test]
+fn test_exit_codes() {
+    let dir = TempDir::new("exit_codes");
+    let compile_error = dir.file("compile.lox", "print 1 +;");
+    let runtime_error = dir.file("runtime.lox", "print -nil;");
+
+    assert_eq!(rlox(&[compile_error.to_str().unwrap()]).status.code(), Some(65));
+    assert_eq!(rlox(&[runtime_error.to_str().unwrap()]).status.code(), Some(70));
+    assert_eq!(rlox(&["a.lox", "b.lox"]).status.code(), Some(64));
+}
+
+#[test]
+fn test_errors_point_into_the_file() {
+    let dir = TempDir::new("errors_point");
+    let script = dir.file("bad.lox", "var a = 1;\nprint a +;");
+
+    // stderr isn't a terminal here, so there are no colors either way.
+    for args in [vec![script.to_str().unwrap()], vec!["--no-color", script.to_str().unwrap()]] {
+        let output = rlox(&args);
+        assert_eq!(stderr(&output), format!(
+            "[line 2] Error at ';': Expect expression.\n --> {}:2:10\n  |\n2 | print a +;\n  |          ^\n",
+            script.display(),
+        ));
+    }
+}
+
+#[test]
+fn test_json_errors() {
+    let dir = TempDir::new("json_errors");
+    let script = dir.file("bad.lox", "@\nprint 1 +;");
+
+    let output = rlox(&["--error-format=json", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    let path = script.to_str().unwrap();
+    assert_eq!(stderr(&output), format!(
+        "{{\"severity\":\"error\",\"message\":\"Unexpected character.\",\"file\":\"{}\",\"line\":1,\"column\":1,\"span\":{{\"start\":0,\"end\":1}}}}\n",
+        path,
+    ));
+}
+
+#[test]
+fn test_ieee_math() {
+    let dir = TempDir::new("ieee_math");
+    let script = dir.file("div.lox", "print 1 / 0;");
+
+    let output = rlox(&[script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).starts_with("Division by zero.\n[line 1]"));
+
+    let output = rlox(&["--ieee-math", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "Infinity\n");
+}
+
+#[test]
+fn test_ast_of_broken_file() {
+    let dir = TempDir::new("ast_of_broken_file");
+    let script = dir.file("broken.lox", "print 1;\nvar = 2;\nprint 3;");
+
+    let output = rlox(&["--ast", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(65));
+    assert_eq!(stdout(&output), "(print 1)\n(error var = 2 ;)\n(print 3)\n");
+    assert!(stderr(&output).contains("Expect variable name."));
+}
+
+#[test]
+fn test_deterministic() {
+    let dir = TempDir::new("deterministic");
+    let script = dir.file("random.lox", "sleep(250); print clock(); print random(); print {\"z\": 1, \"a\": 2};");
+
+    let first = rlox(&["--deterministic", script.to_str().unwrap()]);
+    assert_eq!(first.status.code(), Some(0));
+    assert!(stdout(&first).starts_with("0.25\n"));
+    assert!(stdout(&first).ends_with("{\"z\": 1, \"a\": 2}\n"));
+
+    let second = rlox(&["--deterministic", script.to_str().unwrap()]);
+    assert_eq!(stdout(&first), stdout(&second));
+}
+
+#[test]
+fn test_stack_overflow() {
+    let dir = TempDir::new("stack_overflow");
+    let script = dir.file("recurse.lox", "fun f() { f(); }\nf();");
+
+    let output = rlox(&["--no-color", script.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(70));
+    assert!(stderr(&output).starts_with("Stack overflow.\n[line 1] in f()\n[previous line repeated"));
+}
+
+#[test]
+fn test_missing_file() {
+    let dir = TempDir::new("missing_file");
+    let missing = dir.path().join("missing.lox");
+
+    let output = rlox(&[missing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(74));
+    assert!(stderr(&output).starts_with(&format!("Could not open file '{}': ", missing.display())));
+    assert!(!stderr(&output).contains("panicked"));
+}
+
+#[test]
+fn test_unreadable_file() {
+    let dir = TempDir::new("unreadable_file");
+
+    let output = rlox(&[dir.path().to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(74));
+    assert!(stderr(&output).starts_with("Could not open file"));
+}